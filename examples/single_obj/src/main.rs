@@ -3,7 +3,8 @@ use num::clamp;
 use winit::event::VirtualKeyCode;
 use pseudo_term::{
     env::EnvironmentBuilder,
-    obj::GameObject
+    obj::GameObject,
+    canvas::Canvas
 };
 
 const MOVE_SPD: f32 = 5.0;
@@ -147,18 +148,10 @@ impl GameObject for Player {
         }
     }
 
-    fn draw(&self, text_buf: &mut [[char; 81]; 25]) {
+    fn draw(&self, canvas: &mut Canvas) {
         let anim_frames = self.anims.get(self.cur_anim.as_str()).unwrap();
         let pic = anim_frames[self.anim_frame as usize];
-        for y in 0..5 {
-            for x in 0..3 {
-                if pic[y][x] == '\0' {
-                    continue;
-                }
-                text_buf[self.position.1 as usize + y - 2][self.position.0 as usize + x - 1] =
-                    pic[y][x];
-            }
-        }
+        canvas.blit(self.position.0 as i32 - 1, self.position.1 as i32 - 2, &pic);
     }
 }
 
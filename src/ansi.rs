@@ -0,0 +1,435 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Import ANSI art (.ans) files into cell grids that can be drawn onto a canvas
+
+use std::fs;
+use crate::{
+    cp437,
+    canvas::Canvas,
+    color::Color
+};
+
+const DEFAULT_WIDTH: usize = 80;
+/// Rows to stop at when SAUCE doesn't say, so a cursor moved far down can't eat all the memory
+const MAX_HEIGHT: usize = 10_000;
+const DEFAULT_FG: u8 = 7;
+const DEFAULT_BG: u8 = 0;
+const SAUCE_SIZE: usize = 128;
+const COMMENT_LINE_SIZE: usize = 64;
+const EOF_CHAR: u8 = 0x1A;
+const ESC: u8 = 0x1B;
+
+/// A single character of ANSI art. Colors are xterm 256-color indices (0-15 are the ANSI colors)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AnsiCell {
+    pub glyph: char,
+    pub fg: u8,
+    pub bg: u8
+}
+
+impl Default for AnsiCell {
+    fn default() -> Self {
+        Self {
+            glyph: '\0',
+            fg: DEFAULT_FG,
+            bg: DEFAULT_BG
+        }
+    }
+}
+
+/// Metadata record that ANSI editors append to the end of art files
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sauce {
+    pub title: String,
+    pub author: String,
+    pub group: String,
+    pub date: String,
+    pub data_type: u8,
+    pub file_type: u8,
+    pub width: u16,
+    pub height: u16,
+    pub ice_colors: bool,
+    pub comments: Vec<String>
+}
+
+impl Sauce {
+    /// Find and read the SAUCE record at the end of a file, returning it and where it starts
+    fn parse(bytes: &[u8]) -> Option<(Self, usize)> {
+        if bytes.len() < SAUCE_SIZE {
+            return None;
+        }
+        let start = bytes.len() - SAUCE_SIZE;
+        let rec = &bytes[start..];
+        if &rec[0..7] != b"SAUCE00" {
+            return None;
+        }
+
+        let num_comments = rec[104] as usize;
+        let comment_start = start.checked_sub(5 + num_comments * COMMENT_LINE_SIZE);
+        let mut comments = Vec::new();
+        let mut data_end = start;
+        if let Some(comment_start) = comment_start {
+            if num_comments > 0 && &bytes[comment_start..comment_start + 5] == b"COMNT" {
+                for i in 0..num_comments {
+                    let line_start = comment_start + 5 + i * COMMENT_LINE_SIZE;
+                    comments.push(Self::field(&bytes[line_start..line_start + COMMENT_LINE_SIZE]));
+                }
+                data_end = comment_start;
+            }
+        }
+
+        Some((Self {
+            title: Self::field(&rec[7..42]),
+            author: Self::field(&rec[42..62]),
+            group: Self::field(&rec[62..82]),
+            date: Self::field(&rec[82..90]),
+            data_type: rec[94],
+            file_type: rec[95],
+            width: u16::from_le_bytes([ rec[96], rec[97] ]),
+            height: u16::from_le_bytes([ rec[98], rec[99] ]),
+            ice_colors: rec[105] & 0x01 != 0,
+            comments
+        }, data_end))
+    }
+
+    /// SAUCE strings are CP437, padded with spaces or nulls
+    fn field(bytes: &[u8]) -> String {
+        bytes.iter()
+            .take_while(|byte| **byte != 0)
+            .map(|byte| cp437::to_char(*byte))
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+}
+
+/// A grid of colored cells decoded from an ANSI art file.
+/// Cells never written by the art hold '\0' so they're transparent when drawn
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnsiArt {
+    pub width: usize,
+    pub cells: Vec<Vec<AnsiCell>>,
+    pub sauce: Option<Sauce>
+}
+
+impl AnsiArt {
+    /// Read and decode an ANSI art file
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::from_bytes(&fs::read(path)?))
+    }
+
+    /// Decode ANSI art, interpreting cursor movement and SGR color codes
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let (sauce, data_end) = match Sauce::parse(bytes) {
+            Some((sauce, data_end)) => (Some(sauce), data_end),
+            None => (None, bytes.len())
+        };
+        let data = &bytes[..data_end];
+        let data = match data.iter().position(|byte| *byte == EOF_CHAR) {
+            Some(end) => &data[..end],
+            None => data
+        };
+
+        // SAUCE only knows the width of character based files
+        let width = match &sauce {
+            Some(sauce) if sauce.data_type == 1 && sauce.width > 0 => sauce.width as usize,
+            _ => DEFAULT_WIDTH
+        };
+        let height = match &sauce {
+            Some(sauce) if sauce.data_type == 1 && sauce.height > 0 => sauce.height as usize,
+            _ => MAX_HEIGHT
+        };
+        let ice_colors = sauce.as_ref().map(|sauce| sauce.ice_colors).unwrap_or(false);
+
+        let mut parser = Parser::new(width, height, ice_colors);
+        parser.feed(data);
+        Self {
+            width,
+            cells: parser.cells,
+            sauce
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Draw the art with its top left corner at pos, in palette colors
+    pub fn draw(&self, canvas: &mut Canvas, pos: (i32, i32)) {
+        for (y, row) in self.cells.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                canvas.set_colored(
+                    pos.0 + x as i32, pos.1 + y as i32, cell.glyph,
+                    Color::palette(cell.fg), Color::palette(cell.bg)
+                );
+            }
+        }
+    }
+}
+
+/// Terminal state while walking through the bytes of an ANSI file
+struct Parser {
+    cells: Vec<Vec<AnsiCell>>,
+    width: usize,
+    height: usize,
+    ice_colors: bool,
+
+    cursor: (usize, usize),
+    saved_cursor: (usize, usize),
+    fg: u8,
+    bg: u8,
+    bold: bool,
+    blink: bool,
+    inverse: bool
+}
+
+impl Parser {
+    fn new(width: usize, height: usize, ice_colors: bool) -> Self {
+        Self {
+            cells: Vec::new(),
+            width,
+            height,
+            ice_colors,
+
+            cursor: (0, 0),
+            saved_cursor: (0, 0),
+            fg: DEFAULT_FG,
+            bg: DEFAULT_BG,
+            bold: false,
+            blink: false,
+            inverse: false
+        }
+    }
+
+    fn feed(&mut self, data: &[u8]) {
+        let mut i = 0;
+        while i < data.len() {
+            match data[i] {
+                ESC if data.get(i + 1) == Some(&b'[') => {
+                    // Params run until a final byte in the range '@' to '~'
+                    let params_start = i + 2;
+                    let mut end = params_start;
+                    while end < data.len() && !(0x40..=0x7E).contains(&data[end]) {
+                        end += 1;
+                    }
+                    if end >= data.len() {
+                        break;
+                    }
+                    let params = String::from_utf8_lossy(&data[params_start..end]).to_string();
+                    self.csi(&params, data[end]);
+                    i = end;
+                }, b'\r' => self.cursor.0 = 0,
+                b'\n' => {
+                    self.cursor.0 = 0;
+                    self.cursor.1 += 1;
+                }, byte => self.put(cp437::to_char(byte))
+            }
+            i += 1;
+        }
+    }
+
+    /// Handle a control sequence like ESC[1;31m
+    fn csi(&mut self, params: &str, cmd: u8) {
+        let args = params.trim_start_matches('?').split(';')
+            .map(|arg| arg.parse::<usize>().ok())
+            .collect::<Vec<Option<usize>>>();
+        let arg = |i: usize, default: usize| args.get(i).copied().flatten().unwrap_or(default);
+        match cmd {
+            b'A' => self.cursor.1 = self.cursor.1.saturating_sub(arg(0, 1).max(1)),
+            b'B' => self.cursor.1 = self.cursor.1.saturating_add(arg(0, 1).max(1)),
+            b'C' => {
+                self.cursor.0 = self.cursor.0.saturating_add(arg(0, 1).max(1)).min(self.width - 1);
+            }, b'D' => self.cursor.0 = self.cursor.0.saturating_sub(arg(0, 1).max(1)),
+            b'H' | b'f' => {
+                self.cursor.1 = arg(0, 1).max(1) - 1;
+                self.cursor.0 = (arg(1, 1).max(1) - 1).min(self.width - 1);
+            }, b'J' if arg(0, 0) == 2 => {
+                self.cells.clear();
+                self.cursor = (0, 0);
+            }, b'K' => {
+                let (x, y) = (self.cursor.0.min(self.width), self.cursor.1);
+                let blank = self.blank();
+                self.row(y)[x..].fill(blank);
+            }, b's' => self.saved_cursor = self.cursor,
+            b'u' => self.cursor = self.saved_cursor,
+            b'm' => self.sgr(&args),
+            _ => {}
+        }
+    }
+
+    /// Select graphic rendition, i.e. colors and attributes
+    fn sgr(&mut self, args: &[Option<usize>]) {
+        let mut i = 0;
+        while i < args.len() {
+            match args[i].unwrap_or(0) {
+                0 => {
+                    self.fg = DEFAULT_FG;
+                    self.bg = DEFAULT_BG;
+                    self.bold = false;
+                    self.blink = false;
+                    self.inverse = false;
+                }, 1 => self.bold = true,
+                5 => self.blink = true,
+                7 => self.inverse = true,
+                22 => self.bold = false,
+                25 => self.blink = false,
+                27 => self.inverse = false,
+                code @ 30..=37 => self.fg = (code - 30) as u8,
+                39 => self.fg = DEFAULT_FG,
+                code @ 40..=47 => self.bg = (code - 40) as u8,
+                49 => self.bg = DEFAULT_BG,
+                code @ 90..=97 => self.fg = (code - 90 + 8) as u8,
+                code @ 100..=107 => self.bg = (code - 100 + 8) as u8,
+                code @ (38 | 48) => {
+                    // Extended colors: 5;n for 256-color, 2;r;g;b for truecolor
+                    let color = match args.get(i + 1).copied().flatten() {
+                        Some(5) => {
+                            i += 2;
+                            args.get(i).copied().flatten().map(|n| n.min(255) as u8)
+                        }, Some(2) => {
+                            i += 4;
+                            let channel = |j: usize| args.get(j).copied().flatten().unwrap_or(0);
+                            Some(rgb_to_256(channel(i - 2), channel(i - 1), channel(i)))
+                        }, _ => None
+                    };
+                    if let Some(color) = color {
+                        if code == 38 {
+                            self.fg = color;
+                        } else {
+                            self.bg = color;
+                        }
+                    }
+                }, _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    /// Write a glyph at the cursor with the current colors, wrapping at the art's width
+    fn put(&mut self, glyph: char) {
+        if self.cursor.0 >= self.width {
+            self.cursor.0 = 0;
+            self.cursor.1 += 1;
+        }
+        let (x, y) = self.cursor;
+        let mut cell = self.blank();
+        cell.glyph = glyph;
+        self.row(y)[x] = cell;
+        self.cursor.0 += 1;
+    }
+
+    /// An empty cell in the current colors. Bold brightens fg, and blink brightens bg w/ iCE colors
+    fn blank(&self) -> AnsiCell {
+        let fg = if self.bold && self.fg < 8 { self.fg + 8 } else { self.fg };
+        let bg = if self.blink && self.ice_colors && self.bg < 8 { self.bg + 8 } else { self.bg };
+        let (fg, bg) = if self.inverse { (bg, fg) } else { (fg, bg) };
+        AnsiCell {
+            glyph: ' ',
+            fg,
+            bg
+        }
+    }
+
+    /// The row at y, added if needed. Anything past the art's height lands on its last row
+    fn row(&mut self, y: usize) -> &mut Vec<AnsiCell> {
+        let y = y.min(self.height - 1);
+        while self.cells.len() <= y {
+            self.cells.push(vec![ AnsiCell::default(); self.width ]);
+        }
+        &mut self.cells[y]
+    }
+}
+
+/// Find the closest color in the xterm 6x6x6 color cube
+fn rgb_to_256(r: usize, g: usize, b: usize) -> u8 {
+    let level = |c: usize| ((c.min(255) * 5 + 127) / 255) as u8;
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A SAUCE record for character based art, w/ optional comment lines before it
+    fn sauce(width: u16, height: u16, comments: &[&str]) -> Vec<u8> {
+        let mut bytes = vec![ EOF_CHAR ];
+        if !comments.is_empty() {
+            bytes.extend_from_slice(b"COMNT");
+            for comment in comments {
+                let mut line = comment.as_bytes().to_vec();
+                line.resize(COMMENT_LINE_SIZE, b' ');
+                bytes.extend(line);
+            }
+        }
+        let mut rec = vec![ 0; SAUCE_SIZE ];
+        rec[0..7].copy_from_slice(b"SAUCE00");
+        rec[7..12].copy_from_slice(b"Title");
+        rec[94] = 1;
+        rec[96..98].copy_from_slice(&width.to_le_bytes());
+        rec[98..100].copy_from_slice(&height.to_le_bytes());
+        rec[104] = comments.len() as u8;
+        bytes.extend(rec);
+        bytes
+    }
+
+    fn glyphs(art: &AnsiArt, y: usize) -> String {
+        art.cells[y].iter().map(|cell| cell.glyph).collect()
+    }
+
+    #[test]
+    fn cursor_movement() {
+        let art = AnsiArt::from_bytes(b"a\x1B[2Bb\x1B[1;5Hc\x1B[3Dd\x1B[2Ce\x1B[Af");
+        assert_eq!(art.height(), 3);
+        assert_eq!(&glyphs(&art, 0)[..8], "a\0d\0cef\0");
+        assert_eq!(&glyphs(&art, 2)[..3], "\0b\0");
+    }
+
+    #[test]
+    fn cursor_stays_in_width() {
+        let art = AnsiArt::from_bytes(b"\x1B[999Ca\x1B[1;999Hb");
+        assert_eq!(art.height(), 1);
+        assert_eq!(art.cells[0][DEFAULT_WIDTH - 1].glyph, 'b');
+    }
+
+    #[test]
+    fn huge_cursor_moves_are_bounded() {
+        let art = AnsiArt::from_bytes(b"\x1B[18446744073709551615B\x1B[18446744073709551615Ca");
+        assert_eq!(art.height(), MAX_HEIGHT);
+        assert_eq!(art.cells[MAX_HEIGHT - 1][DEFAULT_WIDTH - 1].glyph, 'a');
+
+        let art = AnsiArt::from_bytes(b"\x1B[99999999Ha");
+        assert_eq!(art.height(), MAX_HEIGHT);
+    }
+
+    #[test]
+    fn sauce_height_bounds_rows() {
+        let mut bytes = b"\x1B[99999999Ha".to_vec();
+        bytes.extend(sauce(40, 3, &[]));
+        let art = AnsiArt::from_bytes(&bytes);
+        assert_eq!(art.width, 40);
+        assert_eq!(art.height(), 3);
+        assert_eq!(art.cells[2][0].glyph, 'a');
+    }
+
+    #[test]
+    fn sauce_is_stripped() {
+        let mut bytes = b"hi".to_vec();
+        bytes.extend(sauce(4, 1, &[ "first", "second" ]));
+        let art = AnsiArt::from_bytes(&bytes);
+        assert_eq!(art.cells, vec![ vec![
+            AnsiCell { glyph: 'h', fg: DEFAULT_FG, bg: DEFAULT_BG },
+            AnsiCell { glyph: 'i', fg: DEFAULT_FG, bg: DEFAULT_BG },
+            AnsiCell::default(),
+            AnsiCell::default()
+        ] ]);
+        let sauce = art.sauce.unwrap();
+        assert_eq!(sauce.title, "Title");
+        assert_eq!(sauce.comments, vec![ "first", "second" ]);
+    }
+
+    #[test]
+    fn colors() {
+        let art = AnsiArt::from_bytes(b"\x1B[1;31;44ma\x1B[0;38;5;200;48;2;255;0;0mb");
+        assert_eq!(art.cells[0][0], AnsiCell { glyph: 'a', fg: 9, bg: 4 });
+        assert_eq!(art.cells[0][1], AnsiCell { glyph: 'b', fg: 200, bg: 196 });
+    }
+}
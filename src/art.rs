@@ -0,0 +1,144 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Built-in game object that draws fixed art, like a room's walls
+
+use std::{
+    collections::HashMap,
+    fs
+};
+use winit::event::VirtualKeyCode;
+use crate::{
+    obj::GameObject,
+    ansi::AnsiArt,
+    camera::WorldBuffer,
+    canvas::Canvas,
+    color::Color
+};
+
+/// Characters drawn at a fixed position every frame. '\0' is left transparent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StaticArt {
+    pos: (i32, i32),
+    lines: Vec<Vec<char>>,
+    /// Each cell's foreground and background. Text art has none, keeping the colors under it
+    colors: Option<Vec<Vec<(Color, Color)>>>,
+    in_world: bool
+}
+
+impl StaticArt {
+    /// Art from lines of text with its top left corner at pos
+    pub fn new(text: &str, pos: (i32, i32)) -> Self {
+        Self {
+            pos,
+            lines: text.lines().map(|line| line.chars().collect()).collect(),
+            colors: None,
+            in_world: false
+        }
+    }
+
+    /// Some ANSI art, glyphs and colors
+    pub fn from_ansi(art: &AnsiArt, pos: (i32, i32)) -> Self {
+        Self {
+            pos,
+            lines: art.cells.iter()
+                .map(|row| row.iter().map(|cell| cell.glyph).collect())
+                .collect(),
+            colors: Some(art.cells.iter()
+                .map(|row| row.iter()
+                    .map(|cell| (Color::palette(cell.fg), Color::palette(cell.bg)))
+                    .collect())
+                .collect()),
+            in_world: false
+        }
+    }
+
+    /// Read art from a file, decoding .ans files as ANSI art and anything else as text
+    pub fn load(path: &str, pos: (i32, i32)) -> Result<Self, Box<dyn std::error::Error>> {
+        if path.to_lowercase().ends_with(".ans") {
+            Ok(Self::from_ansi(&AnsiArt::load(path)?, pos))
+        } else {
+            Ok(Self::new(&fs::read_to_string(path)?, pos))
+        }
+    }
+
+    /// Draw in world space, scrolling with the camera, instead of staying put on screen
+    pub fn set_in_world(&self, in_world: bool) -> Self {
+        let mut new = self.clone();
+        new.in_world = in_world;
+        new
+    }
+
+    /// Every visible character with its position, and its colors if the art has them
+    pub(crate) fn cells(
+            &self) -> impl Iterator<Item = (i32, i32, char, Option<(Color, Color)>)> + '_ {
+        self.lines.iter().enumerate().flat_map(move |(y, line)| {
+            line.iter().enumerate()
+                .filter(|(_, c)| **c != '\0')
+                .map(move |(x, c)| {
+                    let colors = self.colors.as_ref()
+                        .and_then(|colors| colors.get(y)?.get(x).copied());
+                    (self.pos.0 + x as i32, self.pos.1 + y as i32, *c, colors)
+                })
+        })
+    }
+
+    /// Draw onto a canvas offset by some cells
+    pub(crate) fn draw_offset(&self, canvas: &mut Canvas, offset: (i32, i32)) {
+        for (x, y, c, colors) in self.cells() {
+            let (x, y) = (x + offset.0, y + offset.1);
+            match colors {
+                Some((fg, bg)) => canvas.set_colored(x, y, c, fg, bg),
+                None => canvas.set(x, y, c)
+            }
+        }
+    }
+
+    /// Draw into the world
+    pub(crate) fn draw_to_world(&self, world: &mut WorldBuffer) {
+        for (x, y, c, colors) in self.cells() {
+            match colors {
+                Some((fg, bg)) => world.set_colored(x, y, c, fg, bg),
+                None => world.set(x, y, c)
+            }
+        }
+    }
+}
+
+impl GameObject for StaticArt {
+    fn obj_type(&self) -> String {
+        "StaticArt".to_string()
+    }
+
+    fn persistent(&self) -> bool {
+        true
+    }
+
+    fn on_key_pressed(
+            &mut self, _code: VirtualKeyCode,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            _cur_room: &mut String) {}
+
+    fn on_key_released(
+            &mut self, _code: VirtualKeyCode,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            _cur_room: &mut String) {}
+
+    fn update(
+            &mut self, _delta_time: f32,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            _cur_room: &mut String) {}
+
+    fn draw(&self, canvas: &mut Canvas) {
+        if !self.in_world {
+            self.draw_offset(canvas, (0, 0));
+        }
+    }
+
+    fn draw_world(&self, world: &mut WorldBuffer) {
+        if self.in_world {
+            self.draw_to_world(world);
+        }
+    }
+}
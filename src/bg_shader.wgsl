@@ -1,22 +1,43 @@
 // Author(s): Dylan Turner <dylan.turner@tutanota.com>
-// Description: Draw a square background behind text
+// Description: Draw the terminal's background behind the text, with a border around it
 
-var<private> pos: array<vec2<f32>, 6> = array(
-    vec2<f32>(-0.86, 0.96),
-    vec2<f32>(0.86, 0.96),
-    vec2<f32>(0.86, -0.95),
-    vec2<f32>(0.86, -0.95),
-    vec2<f32>(-0.86, -0.95),
-    vec2<f32>(-0.86, 0.96)
+struct Chrome {
+    // Left, top, right and bottom of the grid in pixels
+    grid: vec4<f32>,
+    bg: vec4<f32>,
+    border: vec4<f32>,
+    screen_size: vec2<f32>,
+    border_width: f32,
+    padding: f32
+};
+
+@group(0) @binding(0)
+var<uniform> chrome: Chrome;
+
+var<private> corners: array<vec2<f32>, 6> = array(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(0.0, 0.0)
 );
 
 @vertex
 fn vs_main(@builtin(vertex_index) vertex_index: u32) -> @builtin(position) vec4<f32> {
-    return vec4<f32>(pos[vertex_index].x, pos[vertex_index].y, 0.0, 1.0);
+    let width = chrome.border_width;
+    let outer = chrome.grid + vec4<f32>(-width, -width, width, width);
+    let pixel = mix(outer.xy, outer.zw, corners[vertex_index]);
+    return vec4<f32>(
+        pixel.x / chrome.screen_size.x * 2.0 - 1.0, 1.0 - pixel.y / chrome.screen_size.y * 2.0,
+        0.0, 1.0
+    );
 }
 
 @fragment
-fn fs_main() -> @location(0) vec4f {
-    return vec4(0.0, 0.0, 0.6, 1.0);
+fn fs_main(@builtin(position) pos: vec4<f32>) -> @location(0) vec4<f32> {
+    if all(pos.xy >= chrome.grid.xy) && all(pos.xy < chrome.grid.zw) {
+        return chrome.bg;
+    }
+    return chrome.border;
 }
-
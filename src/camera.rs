@@ -0,0 +1,198 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! World space bigger than the screen, and the cameras and viewports that pick what's shown
+
+use crate::{
+    canvas::{
+        SCREEN_WIDTH, SCREEN_HEIGHT
+    }, color::Color
+};
+
+/// A room's worth of characters in world coordinates. Out of bounds writes are dropped.
+/// Like the canvas, cells keep their colors when only their character is set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorldBuffer {
+    width: usize,
+    height: usize,
+    cells: Vec<char>,
+    colors: Vec<(Color, Color)>
+}
+
+impl WorldBuffer {
+    /// A buffer filled with spaces
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![' '; width * height],
+            colors: vec![(Color::Default, Color::Default); width * height]
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> Option<char> {
+        self.index(x, y).map(|i| self.cells[i])
+    }
+
+    /// Put a character in a cell. '\0' is transparent and leaves the cell as it was
+    pub fn set(&mut self, x: i32, y: i32, c: char) {
+        if c == '\0' {
+            return;
+        }
+        if let Some(i) = self.index(x, y) {
+            self.cells[i] = c;
+        }
+    }
+
+    /// Foreground and background of a cell
+    pub fn get_colors(&self, x: i32, y: i32) -> Option<(Color, Color)> {
+        self.index(x, y).map(|i| self.colors[i])
+    }
+
+    /// Recolor a cell, leaving its character
+    pub fn set_colors(&mut self, x: i32, y: i32, fg: Color, bg: Color) {
+        if let Some(i) = self.index(x, y) {
+            self.colors[i] = (fg, bg);
+        }
+    }
+
+    /// Set a character and its colors together
+    pub fn set_colored(&mut self, x: i32, y: i32, c: char, fg: Color, bg: Color) {
+        self.set(x, y, c);
+        self.set_colors(x, y, fg, bg);
+    }
+
+    /// Write a line of text starting at a position. '\0' leaves a cell as it was
+    pub fn put_str(&mut self, x: i32, y: i32, text: &str) {
+        for (i, c) in text.chars().enumerate() {
+            self.set(x + i as i32, y, c);
+        }
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            None
+        } else {
+            Some(y as usize * self.width + x as usize)
+        }
+    }
+}
+
+/// The top left corner of a viewport in world space, kept inside the world
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Camera {
+    pub pos: (i32, i32),
+
+    /// How far the screen shakes in cells, and for how many more seconds
+    shake: Option<(f32, f32)>,
+    time: f32
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Center a view of some size on a world position
+    pub fn follow(&mut self, target: (i32, i32), view_size: (usize, usize)) {
+        self.pos = (target.0 - view_size.0 as i32 / 2, target.1 - view_size.1 as i32 / 2);
+    }
+
+    /// Keep a view of some size inside a world. Worlds smaller than the view sit at 0, 0
+    pub fn clamp(&mut self, world_size: (usize, usize), view_size: (usize, usize)) {
+        let max_x = world_size.0.saturating_sub(view_size.0) as i32;
+        let max_y = world_size.1.saturating_sub(view_size.1) as i32;
+        self.pos = (self.pos.0.clamp(0, max_x), self.pos.1.clamp(0, max_y));
+    }
+
+    /// Shake the screen up to some number of cells for some seconds, replacing any shake going
+    pub fn shake(&mut self, magnitude: f32, duration: f32) {
+        self.shake = Some((magnitude, duration));
+    }
+
+    /// Count down the shake
+    pub fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+        self.shake = self.shake
+            .map(|(magnitude, time_left)| (magnitude, time_left - delta_time))
+            .filter(|(_, time_left)| *time_left > 0.0);
+    }
+
+    /// Where the screen's top left corner is actually drawn from, shake included.
+    /// The shake is worked out from time, so it's the same every run
+    pub fn view_pos(&self) -> (i32, i32) {
+        match self.shake {
+            Some((magnitude, _)) => (
+                self.pos.0 + ((self.time * 53.0).sin() * magnitude).round() as i32,
+                self.pos.1 + ((self.time * 37.0).cos() * magnitude).round() as i32
+            ), None => self.pos
+        }
+    }
+}
+
+/// A rectangle of the screen with its own camera, showing only objects on some layers.
+/// Objects' draws are clipped to it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Viewport {
+    pub(crate) pos: (usize, usize),
+    pub(crate) size: (usize, usize),
+    pub(crate) layers: Option<Vec<i32>>,
+    pub(crate) follow_layer: Option<i32>,
+    pub(crate) room_art: bool,
+    pub camera: Camera
+}
+
+impl Viewport {
+    /// A viewport showing every layer and the room's art, following any camera target
+    pub fn new(pos: (usize, usize), size: (usize, usize)) -> Self {
+        let pos = (pos.0.min(SCREEN_WIDTH), pos.1.min(SCREEN_HEIGHT));
+        Self {
+            pos,
+            size: (size.0.min(SCREEN_WIDTH - pos.0), size.1.min(SCREEN_HEIGHT - pos.1)),
+            layers: None,
+            follow_layer: None,
+            room_art: true,
+            camera: Camera::new()
+        }
+    }
+
+    /// The whole screen
+    pub fn full_screen() -> Self {
+        Self::new((0, 0), (SCREEN_WIDTH, SCREEN_HEIGHT))
+    }
+
+    /// Only show objects on these layers
+    pub fn set_layers(&self, layers: &[i32]) -> Self {
+        let mut new = self.clone();
+        new.layers = Some(layers.to_vec());
+        new
+    }
+
+    /// Only follow camera targets of objects on this layer, like one player in split-screen
+    pub fn set_follow_layer(&self, layer: Option<i32>) -> Self {
+        let mut new = self.clone();
+        new.follow_layer = layer;
+        new
+    }
+
+    /// Whether the room's background and foreground show here. Turn it off for side panels
+    pub fn set_room_art(&self, room_art: bool) -> Self {
+        let mut new = self.clone();
+        new.room_art = room_art;
+        new
+    }
+
+    pub fn shows_layer(&self, layer: i32) -> bool {
+        self.layers.as_ref().map(|layers| layers.contains(&layer)).unwrap_or(true)
+    }
+
+    pub fn follows_layer(&self, layer: i32) -> bool {
+        self.shows_layer(layer) && self.follow_layer.map(|follow| follow == layer).unwrap_or(true)
+    }
+}
@@ -0,0 +1,537 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Safe drawing onto the screen's character grid. Everything is clipped, so objects can't crash
+//! the game or overwrite the line endings
+
+use std::ops::Range;
+use crate::{
+    color::Color,
+    text::{
+        self, Align
+    }
+};
+
+/// Size of the visible grid in cells
+pub(crate) const SCREEN_WIDTH: usize = 80;
+pub(crate) const SCREEN_HEIGHT: usize = 25;
+
+const BASE_BUFF: [[char; 81]; 25] = [
+    [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\n'
+    ], [
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
+        '\0'
+    ]
+];
+
+/// Characters used to draw a box's edges
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BoxStyle {
+    pub horizontal: char,
+    pub vertical: char,
+    pub top_left: char,
+    pub top_right: char,
+    pub bottom_left: char,
+    pub bottom_right: char
+}
+
+impl BoxStyle {
+    pub const ASCII: Self = Self {
+        horizontal: '-', vertical: '|',
+        top_left: '+', top_right: '+', bottom_left: '+', bottom_right: '+'
+    };
+    pub const SINGLE: Self = Self {
+        horizontal: '─', vertical: '│',
+        top_left: '┌', top_right: '┐', bottom_left: '└', bottom_right: '┘'
+    };
+    pub const DOUBLE: Self = Self {
+        horizontal: '═', vertical: '║',
+        top_left: '╔', top_right: '╗', bottom_left: '╚', bottom_right: '╝'
+    };
+    pub const ROUNDED: Self = Self {
+        horizontal: '─', vertical: '│',
+        top_left: '╭', top_right: '╮', bottom_left: '╰', bottom_right: '╯'
+    };
+}
+
+/// The 80x25 grid of characters objects draw onto. Writes outside the grid (or outside the
+/// viewport being drawn) are dropped, and '\0' is transparent, leaving what's underneath.
+/// Each cell also has a foreground and background color, which stay put when only its character
+/// is set, so text can be written over something colored in
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Canvas {
+    cells: [[char; 81]; 25],
+    colors: [[(Color, Color); SCREEN_WIDTH]; SCREEN_HEIGHT],
+
+    /// Left, top, right and bottom edges (exclusive) of the part that can be drawn on
+    clip: (i32, i32, i32, i32)
+}
+
+impl Default for Canvas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Canvas {
+    /// A blank canvas
+    pub fn new() -> Self {
+        Self {
+            cells: BASE_BUFF,
+            colors: [[(Color::Default, Color::Default); SCREEN_WIDTH]; SCREEN_HEIGHT],
+            clip: (0, 0, SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32)
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        SCREEN_WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        SCREEN_HEIGHT
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> Option<char> {
+        if (0..SCREEN_WIDTH as i32).contains(&x) && (0..SCREEN_HEIGHT as i32).contains(&y) {
+            Some(self.cells[y as usize][x as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Put a character in a cell. Control characters like '\n' are drawn as spaces
+    pub fn set(&mut self, x: i32, y: i32, c: char) {
+        let (left, top, right, bottom) = self.clip;
+        if c != '\0' && (left..right).contains(&x) && (top..bottom).contains(&y) {
+            self.cells[y as usize][x as usize] = if c.is_control() { ' ' } else { c };
+        }
+    }
+
+    /// Foreground and background of a cell
+    pub fn get_colors(&self, x: i32, y: i32) -> Option<(Color, Color)> {
+        if (0..SCREEN_WIDTH as i32).contains(&x) && (0..SCREEN_HEIGHT as i32).contains(&y) {
+            Some(self.colors[y as usize][x as usize])
+        } else {
+            None
+        }
+    }
+
+    /// Recolor a cell, leaving its character
+    pub fn set_colors(&mut self, x: i32, y: i32, fg: Color, bg: Color) {
+        let (left, top, right, bottom) = self.clip;
+        if (left..right).contains(&x) && (top..bottom).contains(&y) {
+            self.colors[y as usize][x as usize] = (fg, bg);
+        }
+    }
+
+    /// Set a character and its colors together
+    pub fn set_colored(&mut self, x: i32, y: i32, c: char, fg: Color, bg: Color) {
+        self.set(x, y, c);
+        self.set_colors(x, y, fg, bg);
+    }
+
+    /// Write text starting at a position. New lines go back to the starting column
+    pub fn print_str(&mut self, x: i32, y: i32, text: &str) {
+        for (line_y, line) in text.lines().enumerate() {
+            for (line_x, c) in line.chars().enumerate() {
+                self.set(x + line_x as i32, y + line_y as i32, c);
+            }
+        }
+    }
+
+    /// Same as print_str, in some colors
+    pub fn print_colored(&mut self, x: i32, y: i32, text: &str, fg: Color, bg: Color) {
+        for (line_y, line) in text.lines().enumerate() {
+            for (line_x, c) in line.chars().enumerate() {
+                self.set_colored(x + line_x as i32, y + line_y as i32, c, fg, bg);
+            }
+        }
+    }
+
+    /// Recolor a rectangle, leaving its characters
+    pub fn color_rect(
+            &mut self, x: i32, y: i32, width: usize, height: usize, fg: Color, bg: Color) {
+        let (xs, ys) = self.clip_rect(x, y, width, height);
+        for cell_y in ys {
+            for cell_x in xs.clone() {
+                self.set_colors(cell_x, cell_y, fg, bg);
+            }
+        }
+    }
+
+    /// Fill a rectangle with one character
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: usize, height: usize, c: char) {
+        let (xs, ys) = self.clip_rect(x, y, width, height);
+        for cell_y in ys {
+            for cell_x in xs.clone() {
+                self.set(cell_x, cell_y, c);
+            }
+        }
+    }
+
+    /// The columns and rows of a rectangle that are inside the clip
+    fn clip_rect(
+            &self, x: i32, y: i32, width: usize, height: usize) -> (Range<i32>, Range<i32>) {
+        let (left, top, right, bottom) = self.clip;
+        let clip_width = width.min(i32::MAX as usize) as i32;
+        let clip_height = height.min(i32::MAX as usize) as i32;
+        (
+            x.max(left)..x.saturating_add(clip_width).min(right),
+            y.max(top)..y.saturating_add(clip_height).min(bottom)
+        )
+    }
+
+    /// Outline a rectangle. Boxes under 2 cells wide or tall aren't drawn
+    pub fn draw_box(&mut self, x: i32, y: i32, width: usize, height: usize, style: BoxStyle) {
+        if width < 2 || height < 2 {
+            return;
+        }
+        let (right, bottom) = (x + width as i32 - 1, y + height as i32 - 1);
+        for line_x in x + 1..right {
+            self.set(line_x, y, style.horizontal);
+            self.set(line_x, bottom, style.horizontal);
+        }
+        for line_y in y + 1..bottom {
+            self.set(x, line_y, style.vertical);
+            self.set(right, line_y, style.vertical);
+        }
+        self.set(x, y, style.top_left);
+        self.set(right, y, style.top_right);
+        self.set(x, bottom, style.bottom_left);
+        self.set(right, bottom, style.bottom_right);
+    }
+
+    /// Outline a rectangle with a title set into the top edge, shortened to fit if it's too long
+    pub fn draw_frame(
+            &mut self, x: i32, y: i32, width: usize, height: usize,
+            style: BoxStyle, title: &str) {
+        self.draw_box(x, y, width, height, style);
+        if width >= 5 && !title.is_empty() {
+            self.print_str(x + 1, y, &format!(" {} ", text::truncate(title, width - 4)));
+        }
+    }
+
+    /// Write one line of text aligned in a width, shortened with '…' if it doesn't fit
+    pub fn print_aligned(&mut self, x: i32, y: i32, width: usize, text: &str, align: Align) {
+        let line = text::truncate(text, width);
+        let offset = align.offset(line.chars().count(), width);
+        self.print_str(x + offset as i32, y, &line);
+    }
+
+    /// Word wrap text into a rectangle, aligning each line. If it runs out of room, the last
+    /// line ends in '…'. Returns how many lines the text needed, which may be more than fit
+    pub fn print_wrapped(
+            &mut self, x: i32, y: i32, width: usize, height: usize,
+            text: &str, align: Align) -> usize {
+        let lines = text::wrap(text, width);
+        for (i, line) in lines.iter().take(height).enumerate() {
+            if i + 1 == height && lines.len() > height {
+                // Always leave room for the ellipsis, even if this line would fit
+                let cut = line.chars().take(width.saturating_sub(1)).collect::<String>();
+                self.print_aligned(x, y + i as i32, width, &format!("{}…", cut), align);
+            } else {
+                self.print_aligned(x, y + i as i32, width, line, align);
+            }
+        }
+        lines.len()
+    }
+
+    /// Draw a straight line of a character between two points, ends included
+    pub fn draw_line(&mut self, from: (i32, i32), to: (i32, i32), c: char) {
+        // Bresenham's
+        let (dx, dy) = ((to.0 - from.0).abs(), -(to.1 - from.1).abs());
+        let (step_x, step_y) = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+        let (mut x, mut y) = from;
+        let mut err = dx + dy;
+        loop {
+            self.set(x, y, c);
+            if (x, y) == to {
+                break;
+            }
+            let err2 = 2 * err;
+            if err2 >= dy {
+                err += dy;
+                x += step_x;
+            }
+            if err2 <= dx {
+                err += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Copy rows of characters, like a sprite's frame, with their top left corner at a position
+    pub fn blit<R: AsRef<[char]>>(&mut self, x: i32, y: i32, rows: &[R]) {
+        for (row_y, row) in rows.iter().enumerate() {
+            for (row_x, c) in row.as_ref().iter().enumerate() {
+                self.set(x + row_x as i32, y + row_y as i32, *c);
+            }
+        }
+    }
+
+    /// The characters as rows, each ending in a new line except the last
+    pub fn buffer(&self) -> &[[char; 81]; 25] {
+        &self.cells
+    }
+
+    /// Each cell's foreground and background, row by row
+    pub fn color_buffer(&self) -> &[[(Color, Color); SCREEN_WIDTH]; SCREEN_HEIGHT] {
+        &self.colors
+    }
+
+    /// Limit drawing to a rectangle, for drawing a viewport
+    pub(crate) fn set_clip(&mut self, pos: (usize, usize), size: (usize, usize)) {
+        self.clip = (
+            pos.0 as i32, pos.1 as i32, (pos.0 + size.0) as i32, (pos.1 + size.1) as i32
+        );
+    }
+
+    pub(crate) fn reset_clip(&mut self) {
+        self.clip = (0, 0, SCREEN_WIDTH as i32, SCREEN_HEIGHT as i32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(canvas: &Canvas, y: usize) -> String {
+        canvas.buffer()[y][..SCREEN_WIDTH].iter().collect::<String>().trim_end().to_string()
+    }
+
+    #[test]
+    fn writes_outside_the_grid_are_dropped() {
+        let mut canvas = Canvas::new();
+        canvas.set(-1, 0, 'a');
+        canvas.set(SCREEN_WIDTH as i32, 0, 'a');
+        canvas.set(0, SCREEN_HEIGHT as i32, 'a');
+        canvas.print_str(78, 0, "abc");
+        assert_eq!(canvas.buffer()[0][78..], [ 'a', 'b', '\n' ]);
+        assert_eq!(canvas.get(-1, 0), None);
+    }
+
+    #[test]
+    fn clip_limits_drawing() {
+        let mut canvas = Canvas::new();
+        canvas.set_clip((2, 1), (3, 2));
+        canvas.fill_rect(0, 0, 10, 10, '#');
+        canvas.color_rect(-5, -5, usize::MAX, usize::MAX, Color::palette(1), Color::palette(2));
+        canvas.reset_clip();
+        canvas.set(0, 0, '@');
+        assert_eq!(row(&canvas, 0), "@");
+        assert_eq!(row(&canvas, 1), "  ###");
+        assert_eq!(row(&canvas, 2), "  ###");
+        assert_eq!(row(&canvas, 3), "");
+        assert_eq!(canvas.get_colors(2, 1), Some((Color::palette(1), Color::palette(2))));
+        assert_eq!(canvas.get_colors(5, 1), Some((Color::Default, Color::Default)));
+    }
+
+    #[test]
+    fn control_chars_are_spaces_and_nul_is_transparent() {
+        let mut canvas = Canvas::new();
+        canvas.print_str(0, 0, "abc");
+        canvas.set(0, 0, '\0');
+        canvas.set(1, 0, '\t');
+        canvas.set(2, 0, '\r');
+        assert_eq!(row(&canvas, 0), "a");
+        assert_eq!(canvas.buffer()[0][SCREEN_WIDTH], '\n');
+    }
+
+    #[test]
+    fn print_wrapped() {
+        let mut canvas = Canvas::new();
+        let lines = canvas.print_wrapped(0, 0, 10, 2, "the quick brown fox jumps", Align::Right);
+        assert_eq!(lines, 3);
+        assert_eq!(row(&canvas, 0), " the quick");
+        assert_eq!(row(&canvas, 1), "brown fox…");
+        assert_eq!(row(&canvas, 2), "");
+
+        let mut canvas = Canvas::new();
+        assert_eq!(canvas.print_wrapped(0, 0, 7, 1, "centered", Align::Center), 2);
+        assert_eq!(row(&canvas, 0), "center…");
+    }
+
+    #[test]
+    fn draw_line() {
+        let mut canvas = Canvas::new();
+        canvas.draw_line((0, 0), (4, 2), '*');
+        assert_eq!(row(&canvas, 0), "*");
+        assert_eq!(row(&canvas, 1), " **");
+        assert_eq!(row(&canvas, 2), "   **");
+
+        let mut canvas = Canvas::new();
+        canvas.draw_line((1, 3), (1, 0), '|');
+        canvas.draw_line((-5, 4), (2, 4), '-');
+        assert_eq!((0..4).map(|y| canvas.get(1, y)).collect::<Vec<_>>(), vec![ Some('|'); 4 ]);
+        assert_eq!(row(&canvas, 4), "---");
+    }
+}
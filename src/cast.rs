@@ -0,0 +1,128 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Record the text buffer to an asciinema v2 (.cast) file as it changes
+
+use std::{
+    fs::File,
+    io::{
+        BufWriter, Write
+    }, time::{
+        SystemTime, UNIX_EPOCH
+    }
+};
+use crate::{
+    canvas::Canvas,
+    color::{
+        self, Color
+    }, diff::FrameDiff,
+    theme::Theme
+};
+
+/// A file path to save a recording to based on the current time
+pub fn default_path() -> String {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or(0);
+    format!("recording_{}.cast", millis)
+}
+
+/// Writes a frame to the cast file each time the screen changes. Colors are written out as
+/// they look in the theme, since players of the recording have their own palettes
+pub struct CastRecorder {
+    out: BufWriter<File>,
+    theme: Theme,
+    /// Colors the terminal is currently writing in
+    pen: (Color, Color),
+    diff: FrameDiff
+}
+
+impl CastRecorder {
+    /// Start a new recording, writing the header right away
+    pub fn create(
+            path: &str, title: &str,
+            theme: &Theme) -> Result<Self, Box<dyn std::error::Error>> {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0);
+        let mut out = BufWriter::new(File::create(path)?);
+        writeln!(
+            out, "{{\"version\": 2, \"width\": 80, \"height\": 25, \"timestamp\": {}, \"title\": {}}}",
+            timestamp, json_str(title)
+        )?;
+        Ok(Self {
+            out,
+            theme: theme.clone(),
+            pen: (Color::Default, Color::Default),
+            diff: FrameDiff::new()
+        })
+    }
+
+    /// Switch to a new theme, redrawing the whole screen in it next frame
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.theme = theme.clone();
+        self.diff.reset();
+    }
+
+    /// Write out whatever changed since the last frame, time being seconds since recording began
+    pub fn record(
+            &mut self, time: f64,
+            frame: &Canvas) -> Result<(), Box<dyn std::error::Error>> {
+        let mut output = String::new();
+        if !self.diff.has_frame() {
+            // First frame draws everything, hiding the cursor so it doesn't sit in the art
+            self.pen = (Color::Default, Color::Default);
+            let (fg, bg) = self.theme.resolve(self.pen);
+            output.push_str("\x1b[?25l");
+            output.push_str(&color::escape(fg, bg));
+            output.push_str("\x1b[2J");
+        }
+
+        // Only move the cursor to and rewrite runs of changed cells, switching colors as needed
+        for run in self.diff.update(frame) {
+            output.push_str(&format!("\x1b[{};{}H", run.y + 1, run.x + 1));
+            for (c, colors) in run.text.chars().zip(run.colors) {
+                if colors != self.pen {
+                    let (fg, bg) = self.theme.resolve(colors);
+                    output.push_str(&color::escape(fg, bg));
+                    self.pen = colors;
+                }
+                output.push(cell_char(c));
+            }
+        }
+
+        if !output.is_empty() {
+            writeln!(self.out, "[{:.6}, \"o\", {}]", time, json_str(&output))?;
+        }
+        Ok(())
+    }
+
+    /// Restore the terminal and make sure everything is written out
+    pub fn finish(mut self, time: f64) -> Result<(), Box<dyn std::error::Error>> {
+        writeln!(self.out, "[{:.6}, \"o\", {}]", time, json_str("\x1b[0m\x1b[?25h"))?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Characters that would mess with a terminal are drawn as spaces
+fn cell_char(c: char) -> char {
+    if c.is_control() { ' ' } else { c }
+}
+
+/// Quote and escape a string for JSON
+fn json_str(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+    quoted
+}
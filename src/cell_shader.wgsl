@@ -0,0 +1,71 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+// Description: Draw each cell of the grid as a quad showing a tile from the atlas
+
+struct Grid {
+    origin: vec2<f32>,
+    cell_size: vec2<f32>,
+    screen_size: vec2<f32>,
+    tile_size: vec2<f32>,
+    atlas_columns: u32,
+    grid_columns: u32
+};
+
+@group(0) @binding(0)
+var<uniform> grid: Grid;
+@group(0) @binding(1)
+var atlas: texture_2d<f32>;
+
+struct CellIn {
+    @location(0) tile: u32,
+    @location(1) fg: vec4<f32>,
+    @location(2) bg: vec4<f32>
+};
+
+struct VertexOut {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) local: vec2<f32>,
+    @location(1) @interpolate(flat) tile: u32,
+    @location(2) fg: vec4<f32>,
+    @location(3) bg: vec4<f32>
+};
+
+var<private> corners: array<vec2<f32>, 6> = array(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+    vec2<f32>(0.0, 0.0)
+);
+
+@vertex
+fn vs_main(
+        @builtin(vertex_index) vertex_index: u32, @builtin(instance_index) instance_index: u32,
+        cell: CellIn) -> VertexOut {
+    let corner = corners[vertex_index];
+    let pos = vec2<f32>(
+        f32(instance_index % grid.grid_columns), f32(instance_index / grid.grid_columns)
+    );
+    let pixel = grid.origin + (pos + corner) * grid.cell_size;
+    var out: VertexOut;
+    out.clip_pos = vec4<f32>(
+        pixel.x / grid.screen_size.x * 2.0 - 1.0, 1.0 - pixel.y / grid.screen_size.y * 2.0,
+        0.0, 1.0
+    );
+    out.local = corner;
+    out.tile = cell.tile;
+    out.fg = cell.fg;
+    out.bg = cell.bg;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    // Read texels directly so tiles stay crisp and never pick up their neighbors' edges
+    let tile_size = vec2<u32>(grid.tile_size);
+    let tile_origin = vec2<u32>(in.tile % grid.atlas_columns, in.tile / grid.atlas_columns)
+        * tile_size;
+    let texel = min(vec2<u32>(in.local * grid.tile_size), tile_size - vec2<u32>(1u, 1u));
+    let color = textureLoad(atlas, tile_origin + texel, 0);
+    return mix(in.bg, vec4<f32>(in.fg.rgb * color.rgb, in.fg.a), color.a);
+}
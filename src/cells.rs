@@ -0,0 +1,307 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Draw the grid as one instanced quad per cell, each showing a tile from an atlas texture.
+//! Only cells that changed since the last frame are uploaded
+
+use std::borrow::Cow;
+use wgpu::{
+    Device, Queue, TextureFormat, Buffer, BufferDescriptor, BufferUsages, BindGroup,
+    BindGroupLayout, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, BindingResource, BufferBindingType, ShaderStages,
+    TextureSampleType, TextureViewDimension, Texture, TextureDescriptor, TextureDimension,
+    TextureUsages, TextureViewDescriptor,
+    TextureAspect, ImageCopyTexture, ImageDataLayout, Origin3d, Extent3d, ShaderSource,
+    ShaderModuleDescriptor, PipelineLayoutDescriptor, RenderPipelineDescriptor, RenderPipeline,
+    RenderPass, VertexState, FragmentState, ColorTargetState, BlendState, ColorWrites,
+    VertexBufferLayout, VertexStepMode, VertexAttribute, vertex_attr_array
+};
+use crate::{
+    canvas::{
+        SCREEN_WIDTH, SCREEN_HEIGHT
+    }, render::WINDOW_SIZE
+};
+
+/// Tile, foreground and background, all four bytes a piece.
+/// Where a cell goes comes from its place in the buffer
+const INSTANCE_SIZE: usize = 4 * (1 + 4 + 4);
+const INSTANCE_ATTRIBUTES: [VertexAttribute; 3] = vertex_attr_array![
+    0 => Uint32, 1 => Float32x4, 2 => Float32x4
+];
+/// Origin, cell size, screen size and tile size, then the atlas's and grid's columns
+const GRID_SIZE: usize = 4 * (2 * 4 + 2);
+
+/// What one cell of the grid shows
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Cell {
+    pub tile: u32,
+    pub fg: [f32; 4],
+    pub bg: [f32; 4]
+}
+
+/// Draws a grid of cells from an atlas of equally sized tiles, scaled as large as fits the
+/// window. Tiles are scaled up by whole numbers when there's room so pixel art stays even
+pub(crate) struct CellRenderer {
+    pipeline: RenderPipeline,
+    bind_group_layout: BindGroupLayout,
+    grid_buffer: Buffer,
+    /// The atlas texture and the bind group showing it
+    atlas: Option<(Texture, BindGroup)>,
+    atlas_columns: u32,
+    /// Holds one instance per cell, so it's remade when the grid changes size
+    instances: Buffer,
+    /// What's in the instance buffer, to compare new frames against
+    uploaded: Vec<Option<Cell>>,
+    grid_size: (usize, usize),
+    tile_size: (u32, u32),
+    origin: (f32, f32),
+    cell_size: (f32, f32)
+}
+
+impl CellRenderer {
+    /// A renderer for the screen sized grid, which set_grid_size can change
+    pub fn new(device: &Device, format: TextureFormat, tile_size: (u32, u32)) -> Self {
+        let grid_size = (SCREEN_WIDTH, SCREEN_HEIGHT);
+        let (origin, cell_size) = Self::layout(tile_size, grid_size);
+
+        let grid_buffer = device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: GRID_SIZE as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                    }, count: None
+                }, BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false
+                    }, count: None
+                }
+            ]
+        });
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: None,
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("cell_shader.wgsl")))
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[ &bind_group_layout ],
+            push_constant_ranges: &[]
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    VertexBufferLayout {
+                        array_stride: INSTANCE_SIZE as u64,
+                        step_mode: VertexStepMode::Instance,
+                        attributes: &INSTANCE_ATTRIBUTES
+                    }
+                ]
+            }, fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL
+                })]
+            }), primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            grid_buffer,
+            atlas: None,
+            atlas_columns: 0,
+            instances: Self::create_instances(device, grid_size),
+            uploaded: vec![None; grid_size.0 * grid_size.1],
+            grid_size,
+            tile_size,
+            origin,
+            cell_size
+        }
+    }
+
+    /// Where the grid's top left corner goes and how big its cells are to fit it in the window
+    fn layout(tile_size: (u32, u32), grid_size: (usize, usize)) -> ((f32, f32), (f32, f32)) {
+        let (tile_width, tile_height) = tile_size;
+        let fit = f32::min(
+            WINDOW_SIZE.width as f32 / (tile_width as usize * grid_size.0).max(1) as f32,
+            WINDOW_SIZE.height as f32 / (tile_height as usize * grid_size.1).max(1) as f32
+        );
+        let scale = if fit >= 1.0 { fit.floor() } else { fit };
+        let cell_size = (tile_width as f32 * scale, tile_height as f32 * scale);
+        let origin = (
+            (WINDOW_SIZE.width as f32 - cell_size.0 * grid_size.0 as f32) / 2.0,
+            (WINDOW_SIZE.height as f32 - cell_size.1 * grid_size.1 as f32) / 2.0
+        );
+        (origin, cell_size)
+    }
+
+    fn create_instances(device: &Device, grid_size: (usize, usize)) -> Buffer {
+        device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: (INSTANCE_SIZE * grid_size.0 * grid_size.1) as u64,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        })
+    }
+
+    /// Draw a grid of some number of columns and rows from now on, refitting it to the window.
+    /// Returns whether anything changed
+    pub fn set_grid_size(
+            &mut self, device: &Device, queue: &Queue, grid_size: (usize, usize)) -> bool {
+        if grid_size == self.grid_size {
+            return false;
+        }
+        self.grid_size = grid_size;
+        (self.origin, self.cell_size) = Self::layout(self.tile_size, grid_size);
+        self.instances = Self::create_instances(device, grid_size);
+        self.uploaded = vec![None; grid_size.0 * grid_size.1];
+        if self.atlas.is_some() {
+            self.write_grid(queue);
+        }
+        true
+    }
+
+    fn write_grid(&self, queue: &Queue) {
+        let mut grid = Vec::with_capacity(GRID_SIZE);
+        push_f32s(&mut grid, &[
+            self.origin.0, self.origin.1, self.cell_size.0, self.cell_size.1,
+            WINDOW_SIZE.width as f32, WINDOW_SIZE.height as f32,
+            self.tile_size.0 as f32, self.tile_size.1 as f32
+        ]);
+        grid.extend_from_slice(&self.atlas_columns.to_le_bytes());
+        grid.extend_from_slice(&(self.grid_size.0 as u32).to_le_bytes());
+        queue.write_buffer(&self.grid_buffer, 0, &grid);
+    }
+
+    /// Upload the atlas, an RGBA image of tiles in rows. A new texture is only made when the
+    /// atlas changes size
+    pub fn set_atlas(&mut self, device: &Device, queue: &Queue, pixels: &[u8], size: (u32, u32)) {
+        let texture_size = Extent3d {
+            width: size.0,
+            height: size.1,
+            depth_or_array_layers: 1
+        };
+        let same_size = self.atlas.as_ref()
+            .map(|(texture, _)| texture.size() == texture_size)
+            .unwrap_or(false);
+        if !same_size {
+            let texture = device.create_texture(&TextureDescriptor {
+                label: None,
+                size: texture_size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8UnormSrgb,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                view_formats: &[]
+            });
+            let view = texture.create_view(&TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&BindGroupDescriptor {
+                label: None,
+                layout: &self.bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: self.grid_buffer.as_entire_binding()
+                    }, BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(&view)
+                    }
+                ]
+            });
+            self.atlas = Some((texture, bind_group));
+            self.atlas_columns = size.0 / self.tile_size.0;
+            self.write_grid(queue);
+        }
+        if let Some((texture, _)) = &self.atlas {
+            queue.write_texture(
+                ImageCopyTexture {
+                    texture,
+                    mip_level: 0,
+                    origin: Origin3d::ZERO,
+                    aspect: TextureAspect::All
+                }, pixels,
+                ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * size.0),
+                    rows_per_image: Some(size.1)
+                }, texture_size
+            );
+        }
+    }
+
+    /// Where the grid's top left corner is in the window
+    pub fn origin(&self) -> (f32, f32) {
+        self.origin
+    }
+
+    pub fn cell_size(&self) -> (f32, f32) {
+        self.cell_size
+    }
+
+    /// Columns and rows of cells
+    pub fn grid_size(&self) -> (usize, usize) {
+        self.grid_size
+    }
+
+    /// Give every cell, row by row, uploading runs of cells that changed since last time
+    pub fn prepare(&mut self, queue: &Queue, cells: &[Cell]) {
+        let mut i = 0;
+        let cell_count = cells.len().min(self.uploaded.len());
+        while i < cell_count {
+            if self.uploaded[i] == Some(cells[i]) {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            let mut data = Vec::new();
+            while i < cell_count && self.uploaded[i] != Some(cells[i]) {
+                let cell = cells[i];
+                data.extend_from_slice(&cell.tile.to_le_bytes());
+                push_f32s(&mut data, &cell.fg);
+                push_f32s(&mut data, &cell.bg);
+                self.uploaded[i] = Some(cell);
+                i += 1;
+            }
+            queue.write_buffer(&self.instances, (start * INSTANCE_SIZE) as u64, &data);
+        }
+    }
+
+    pub fn draw<'a>(&'a self, rpass: &mut RenderPass<'a>) {
+        if let Some((_, bind_group)) = &self.atlas {
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, bind_group, &[]);
+            rpass.set_vertex_buffer(0, self.instances.slice(..));
+            rpass.draw(0..6, 0..self.uploaded.len() as u32);
+        }
+    }
+}
+
+fn push_f32s(bytes: &mut Vec<u8>, values: &[f32]) {
+    for value in values {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+}
@@ -0,0 +1,143 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Requests game objects can make of the engine from inside their callbacks.
+//! Each environment has its own queue, which is where sends go while its objects' callbacks run,
+//! so several environments can run in one program without taking each other's commands
+
+use std::{
+    cell::RefCell,
+    sync::{
+        Arc, Mutex
+    }
+};
+use crate::{
+    theme::Theme,
+    timer::Timer,
+    tween::Tween
+};
+
+thread_local! {
+    /// Queues of the environments running callbacks on this thread, innermost last
+    static ACTIVE: RefCell<Vec<CommandQueue>> = const { RefCell::new(Vec::new()) };
+
+    /// Commands sent on this thread outside of any environment's callbacks, like from a test.
+    /// The next environment to take its commands on this thread gets them
+    static LOOSE: RefCell<Vec<Command>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Something for the engine to do once the current callbacks finish
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Save the next frame to <path>.png, <path>.txt and <path>.ans
+    Screenshot(String),
+
+    /// Start recording to an asciinema .cast file, replacing any recording in progress
+    StartCast(String),
+
+    /// Stop recording, if there's a recording going
+    StopCast,
+
+    /// Start recording video to a .gif or .png (APNG) file, replacing any recording in progress
+    StartVideo(String),
+
+    /// Stop recording video, if there's a recording going
+    StopVideo,
+
+    /// Point a viewport's camera (0 with only one viewport) at a world position for its top
+    /// left corner. Objects with a camera target will pull it back next update
+    MoveCamera(usize, i32, i32),
+
+    /// Shake every viewport up to some number of cells for some seconds
+    ShakeCamera(f32, f32),
+
+    /// Recolor the terminal, like switching to a green phosphor look
+    SetTheme(Theme),
+
+    /// Replace the start of the current theme's palette, recoloring every cell drawn with it
+    SetPalette(Vec<[f32; 4]>),
+
+    /// Change one color of the current theme's palette
+    SetPaletteColor(usize, [f32; 4]),
+
+    /// Change the params of the post-processing effects with a name
+    SetEffectParams(String, [f32; 4]),
+
+    /// Turn the post-processing effects with a name on or off
+    SetEffectEnabled(String, bool),
+
+    /// Start a timer, replacing any timer with the same name
+    StartTimer(Timer),
+
+    /// Stop a timer before it goes off again
+    CancelTimer(String),
+
+    /// Play a tween under a name, for objects with an id or everything in play without one.
+    /// Replaces any tween with the same name
+    StartTween(String, Option<String>, Tween),
+
+    /// Stop a tween where it is, without finishing it
+    StopTween(String),
+
+    /// Run game time faster or slower, 1 being normal speed.
+    /// Updates, timers, tweens and the camera all go by game time
+    SetTimeScale(f32),
+
+    /// Stop or restart game time. Objects don't update while paused, but still get input and
+    /// messages and are still drawn, so a pause menu can unpause
+    SetPaused(bool),
+
+    /// Pass some text on a channel to every object in play, through on_message.
+    /// Message logs listen on ui::LOG_CHANNEL
+    Message(String, String),
+
+    /// Write a snapshot of the game to a save file. Format is picked by extension
+    #[cfg(feature = "serde")]
+    Save(String),
+
+    /// Restore the game from a save file
+    #[cfg(feature = "serde")]
+    Load(String)
+}
+
+/// Queue up a command for the environment whose callbacks are running
+pub fn send(cmd: Command) {
+    let cmd = ACTIVE.with(|active| match active.borrow().last() {
+        Some(queue) => {
+            queue.commands.lock().expect("Command queue poisoned.").push(cmd);
+            None
+        }, None => Some(cmd)
+    });
+    if let Some(cmd) = cmd {
+        LOOSE.with(|loose| loose.borrow_mut().push(cmd));
+    }
+}
+
+/// One environment's commands
+#[derive(Clone, Default)]
+pub(crate) struct CommandQueue {
+    commands: Arc<Mutex<Vec<Command>>>
+}
+
+impl CommandQueue {
+    /// Send commands on this thread here until the scope is dropped
+    pub fn enter(&self) -> QueueScope {
+        ACTIVE.with(|active| active.borrow_mut().push(self.clone()));
+        QueueScope
+    }
+
+    /// Take all the commands sent since the last time this was called, including ones sent on
+    /// this thread outside of any environment
+    pub fn drain(&self) -> Vec<Command> {
+        let mut commands = LOOSE.with(|loose| std::mem::take(&mut *loose.borrow_mut()));
+        commands.append(&mut self.commands.lock().expect("Command queue poisoned."));
+        commands
+    }
+}
+
+/// While this is around, sends on its thread go to the queue it came from
+pub(crate) struct QueueScope;
+
+impl Drop for QueueScope {
+    fn drop(&mut self) {
+        ACTIVE.with(|active| active.borrow_mut().pop());
+    }
+}
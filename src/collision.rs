@@ -0,0 +1,294 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Which objects overlap, cell by cell. Objects give bounds through GameObject::collider, and
+//! after every update the engine tells each one what it's touching through on_collision
+
+use std::collections::{
+    BTreeSet, HashSet
+};
+use crate::art::StaticArt;
+
+/// The cells an object takes up, in the same cells it draws in, usually world cells
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Bounds {
+    /// A solid rectangle with its top left corner at pos
+    Rect {
+        pos: (i32, i32),
+        size: (usize, usize)
+    },
+
+    /// Only some cells, relative to pos, like the shape of a sprite
+    Mask {
+        pos: (i32, i32),
+        cells: Vec<(i32, i32)>
+    }
+}
+
+impl Bounds {
+    pub fn rect(x: i32, y: i32, width: usize, height: usize) -> Self {
+        Self::Rect {
+            pos: (x, y),
+            size: (width, height)
+        }
+    }
+
+    /// The cells of a sprite that aren't spaces, with its top left corner at pos
+    pub fn sprite(text: &str, pos: (i32, i32)) -> Self {
+        let cells = text.lines().enumerate().flat_map(|(y, line)| {
+            line.chars().enumerate()
+                .filter(|(_, c)| *c != ' ' && *c != '\0')
+                .map(move |(x, _)| (x as i32, y as i32))
+        }).collect();
+        Self::Mask {
+            pos,
+            cells
+        }
+    }
+
+    /// The cells of some art that aren't spaces or transparent, where the art is
+    pub fn from_art(art: &StaticArt) -> Self {
+        Self::Mask {
+            pos: (0, 0),
+            cells: art.cells()
+                .filter(|(_, _, c, _)| *c != ' ')
+                .map(|(x, y, _, _)| (x, y))
+                .collect()
+        }
+    }
+
+    /// The same shape somewhere else. Masks move by how far their pos changes
+    pub fn set_pos(&self, pos: (i32, i32)) -> Self {
+        match self {
+            Self::Rect { size, .. } => Self::Rect {
+                pos,
+                size: *size
+            }, Self::Mask { cells, .. } => Self::Mask {
+                pos,
+                cells: cells.clone()
+            }
+        }
+    }
+
+    /// Every cell taken up
+    pub fn cells(&self) -> Vec<(i32, i32)> {
+        match self {
+            Self::Rect { pos, size } => (0..size.1 as i32)
+                .flat_map(|y| (0..size.0 as i32).map(move |x| (pos.0 + x, pos.1 + y)))
+                .collect(),
+            Self::Mask { pos, cells } => cells.iter()
+                .map(|(x, y)| (pos.0 + x, pos.1 + y))
+                .collect()
+        }
+    }
+}
+
+/// An object's bounds and what it collides with. Objects are on some collision layers, bits of
+/// layers, and only hear about objects on layers in their mask, so a pickup can notice the
+/// player without the player having to care about every pickup
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Collider {
+    pub(crate) bounds: Bounds,
+    pub(crate) layers: u32,
+    pub(crate) mask: u32
+}
+
+impl Collider {
+    /// Bounds on layer 1 that hear about everything
+    pub fn new(bounds: Bounds) -> Self {
+        Self {
+            bounds,
+            layers: 1,
+            mask: u32::MAX
+        }
+    }
+
+    /// Which layers the object is on, one per bit
+    pub fn set_layers(&self, layers: u32) -> Self {
+        let mut new = self.clone();
+        new.layers = layers;
+        new
+    }
+
+    /// Which layers the object hears about collisions with, one per bit
+    pub fn set_mask(&self, mask: u32) -> Self {
+        let mut new = self.clone();
+        new.mask = mask;
+        new
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    /// Whether touching another collider should be reported to this one
+    pub fn detects(&self, other: &Collider) -> bool {
+        self.mask & other.layers != 0
+    }
+}
+
+/// Each pair of colliders sharing at least one cell, by index. Colliders are swept across by
+/// their left edges so only ones that are close get compared, and rectangles are compared by
+/// their edges, so only masks' cells are ever looked at one by one
+pub(crate) fn overlapping_pairs(colliders: &[Option<Collider>]) -> BTreeSet<(usize, usize)> {
+    let mut shapes = colliders.iter().enumerate()
+        .filter_map(|(index, collider)| Some((index, Shape::new(&collider.as_ref()?.bounds)?)))
+        .collect::<Vec<_>>();
+    shapes.sort_by_key(|(_, shape)| shape.left);
+
+    let mut pairs = BTreeSet::new();
+    for (i, (a, shape_a)) in shapes.iter().enumerate() {
+        let nearby = shapes[i + 1..].iter().take_while(|(_, shape_b)| shape_b.left < shape_a.right);
+        for (b, shape_b) in nearby {
+            if shape_a.overlaps(shape_b) {
+                pairs.insert((*a.min(b), *a.max(b)));
+            }
+        }
+    }
+    pairs
+}
+
+/// Bounds in a form that's quick to compare. Wide enough that no position or size overflows
+struct Shape {
+    /// Edges of the cells taken up, the right and bottom ones exclusive
+    left: i64,
+    top: i64,
+    right: i64,
+    bottom: i64,
+
+    /// Each cell of a mask. Rectangles are all their cells
+    cells: Option<HashSet<(i64, i64)>>
+}
+
+impl Shape {
+    /// None for bounds with no cells, which can't touch anything
+    fn new(bounds: &Bounds) -> Option<Self> {
+        match bounds {
+            Bounds::Rect { pos, size } => {
+                if size.0 == 0 || size.1 == 0 {
+                    return None;
+                }
+                let (left, top) = (pos.0 as i64, pos.1 as i64);
+                let length = |len: usize| len.min(u32::MAX as usize) as i64;
+                Some(Self {
+                    left,
+                    top,
+                    right: left + length(size.0),
+                    bottom: top + length(size.1),
+                    cells: None
+                })
+            }, Bounds::Mask { pos, cells } => {
+                let cells = cells.iter()
+                    .map(|(x, y)| (pos.0 as i64 + *x as i64, pos.1 as i64 + *y as i64))
+                    .collect::<HashSet<_>>();
+                Some(Self {
+                    left: cells.iter().map(|(x, _)| *x).min()?,
+                    top: cells.iter().map(|(_, y)| *y).min()?,
+                    right: cells.iter().map(|(x, _)| *x).max()? + 1,
+                    bottom: cells.iter().map(|(_, y)| *y).max()? + 1,
+                    cells: Some(cells)
+                })
+            }
+        }
+    }
+
+    fn contains(&self, cell: &(i64, i64)) -> bool {
+        (self.left..self.right).contains(&cell.0) && (self.top..self.bottom).contains(&cell.1)
+            && self.cells.as_ref().map(|cells| cells.contains(cell)).unwrap_or(true)
+    }
+
+    fn overlaps(&self, other: &Shape) -> bool {
+        let edges_overlap = self.left < other.right && other.left < self.right
+            && self.top < other.bottom && other.top < self.bottom;
+        if !edges_overlap {
+            return false;
+        }
+        match (&self.cells, &other.cells) {
+            (None, None) => true,
+            (Some(cells), None) => cells.iter().any(|cell| other.contains(cell)),
+            (None, Some(cells)) => cells.iter().any(|cell| self.contains(cell)),
+            (Some(cells), Some(other_cells)) => if cells.len() <= other_cells.len() {
+                cells.iter().any(|cell| other.contains(cell))
+            } else {
+                other_cells.iter().any(|cell| self.contains(cell))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(bounds: &[Bounds]) -> Vec<(usize, usize)> {
+        let colliders = bounds.iter()
+            .map(|bounds| Some(Collider::new(bounds.clone())))
+            .collect::<Vec<_>>();
+        overlapping_pairs(&colliders).into_iter().collect()
+    }
+
+    #[test]
+    fn rect_rect() {
+        assert_eq!(pairs(&[
+            Bounds::rect(0, 0, 4, 4),
+            Bounds::rect(3, 3, 2, 2),
+            Bounds::rect(4, 0, 2, 2),
+            Bounds::rect(-1000, -1000, 1000, 1000)
+        ]), vec![ (0, 1) ]);
+        assert_eq!(pairs(&[ Bounds::rect(0, 0, 0, 5), Bounds::rect(0, 0, 5, 5) ]), vec![]);
+    }
+
+    #[test]
+    fn huge_rects_dont_wrap() {
+        assert_eq!(pairs(&[
+            Bounds::rect(i32::MAX - 1, 0, usize::MAX, usize::MAX),
+            Bounds::rect(i32::MIN, 0, 1, 1),
+            Bounds::rect(i32::MAX, 5, 1, 1)
+        ]), vec![ (0, 2) ]);
+    }
+
+    #[test]
+    fn rect_mask() {
+        let mask = Bounds::sprite("#  \n  #", (10, 10));
+        assert_eq!(pairs(&[ Bounds::rect(11, 10, 1, 2), mask.clone() ]), vec![]);
+        assert_eq!(pairs(&[ Bounds::rect(12, 11, 1, 1), mask.clone() ]), vec![ (0, 1) ]);
+        assert_eq!(pairs(&[ mask, Bounds::rect(0, 0, 11, 11) ]), vec![ (0, 1) ]);
+    }
+
+    #[test]
+    fn mask_mask() {
+        let a = Bounds::sprite("# #", (0, 0));
+        assert_eq!(pairs(&[ a.clone(), Bounds::sprite("#", (1, 0)) ]), vec![]);
+        assert_eq!(pairs(&[ a, Bounds::sprite(" #\n##", (1, -1)) ]), vec![ (0, 1) ]);
+    }
+
+    #[test]
+    fn mask_listing_a_cell_twice() {
+        let mask = Bounds::Mask {
+            pos: (0, 0),
+            cells: vec![ (1, 1), (1, 1) ]
+        };
+        assert_eq!(pairs(&[ mask.clone(), Bounds::rect(1, 1, 1, 1) ]), vec![ (0, 1) ]);
+        assert_eq!(pairs(&[ mask ]), vec![]);
+    }
+
+    #[test]
+    fn only_colliders_count() {
+        let colliders = vec![
+            Some(Collider::new(Bounds::rect(0, 0, 2, 2))),
+            None,
+            Some(Collider::new(Bounds::Mask { pos: (0, 0), cells: Vec::new() })),
+            Some(Collider::new(Bounds::rect(1, 1, 2, 2)))
+        ];
+        assert_eq!(overlapping_pairs(&colliders).into_iter().collect::<Vec<_>>(), vec![ (0, 3) ]);
+    }
+
+    #[test]
+    fn layers_and_masks() {
+        let player = Collider::new(Bounds::rect(0, 0, 1, 1)).set_layers(0b01).set_mask(0b10);
+        let pickup = Collider::new(Bounds::rect(0, 0, 1, 1)).set_layers(0b10).set_mask(0b01);
+        let scenery = Collider::new(Bounds::rect(0, 0, 1, 1)).set_layers(0b100).set_mask(0);
+        assert!(player.detects(&pickup) && pickup.detects(&player));
+        assert!(!player.detects(&scenery) && !scenery.detects(&player));
+        assert!(!player.detects(&player));
+        assert!(Collider::new(Bounds::rect(0, 0, 1, 1)).detects(&scenery));
+    }
+}
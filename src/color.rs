@@ -0,0 +1,75 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Colors cells are drawn in, referenced like a terminal does. The renderer looks them up in the
+//! theme's palette each frame, so remapping the palette recolors everything using it
+
+/// A cell's foreground or background. Named colors are the 16 ANSI ones, palette entries 0-15.
+/// Indexed colors are xterm 256-color palette entries, and Rgb colors skip the palette entirely
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// The theme's text color for foregrounds, or its background color for backgrounds
+    #[default]
+    Default,
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+    Indexed(u8),
+    Rgb(u8, u8, u8)
+}
+
+const NAMED: [Color; 16] = [
+    Color::Black, Color::Red, Color::Green, Color::Yellow,
+    Color::Blue, Color::Magenta, Color::Cyan, Color::White,
+    Color::BrightBlack, Color::BrightRed, Color::BrightGreen, Color::BrightYellow,
+    Color::BrightBlue, Color::BrightMagenta, Color::BrightCyan, Color::BrightWhite
+];
+
+impl Color {
+    /// A palette entry, named if it's one of the 16 ANSI colors
+    pub fn palette(index: u8) -> Self {
+        NAMED.get(index as usize).copied().unwrap_or(Self::Indexed(index))
+    }
+
+    /// Which palette entry this is, if it comes from the palette
+    pub fn index(&self) -> Option<u8> {
+        match self {
+            Self::Default | Self::Rgb(..) => None,
+            Self::Indexed(index) => Some(*index),
+            named => NAMED.iter().position(|color| color == named).map(|index| index as u8)
+        }
+    }
+
+    /// The color to draw with, given the palette and what Default means here
+    pub(crate) fn resolve(&self, palette: &[[f32; 4]], default: [f32; 4]) -> [f32; 4] {
+        match self {
+            Self::Default => default,
+            Self::Rgb(r, g, b) => [ *r as f32 / 255.0, *g as f32 / 255.0, *b as f32 / 255.0, 1.0 ],
+            color => color.index()
+                .and_then(|index| palette.get(index as usize))
+                .copied()
+                .unwrap_or(default)
+        }
+    }
+}
+
+/// Terminal escape codes to switch to a truecolor foreground and background
+pub(crate) fn escape(fg: [f32; 4], bg: [f32; 4]) -> String {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!(
+        "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+        channel(fg[0]), channel(fg[1]), channel(fg[2]),
+        channel(bg[0]), channel(bg[1]), channel(bg[2])
+    )
+}
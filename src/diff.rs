@@ -0,0 +1,82 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Track which cells of the screen changed between frames, so only those get drawn or sent
+
+use crate::{
+    canvas::{
+        Canvas, SCREEN_WIDTH, SCREEN_HEIGHT
+    }, color::Color
+};
+
+/// A row of neighboring cells that changed, starting at a position, with each one's foreground
+/// and background
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CellRun {
+    pub x: usize,
+    pub y: usize,
+    pub text: String,
+    pub colors: Vec<(Color, Color)>
+}
+
+/// Compares each frame given to it with the one before it. There's nothing to compare the first
+/// frame with, so everything in it counts as changed
+#[derive(Clone, Debug, Default)]
+pub struct FrameDiff {
+    last_frame: Option<Canvas>
+}
+
+impl FrameDiff {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether a frame has been seen yet. Until one has, the next frame is a full redraw
+    pub fn has_frame(&self) -> bool {
+        self.last_frame.is_some()
+    }
+
+    /// Forget the last frame so the next one is all changes, like when a new client connects
+    pub fn reset(&mut self) {
+        self.last_frame = None;
+    }
+
+    /// Whether a frame differs from the last one in characters or colors, remembering it for
+    /// next time
+    pub fn changed(&mut self, frame: &Canvas) -> bool {
+        let changed = self.last_frame.as_ref() != Some(frame);
+        self.last_frame = Some(frame.clone());
+        changed
+    }
+
+    /// Runs of cells that differ from the last frame, top to bottom and left to right,
+    /// remembering this frame for next time. The first frame is a run for every line
+    pub fn update(&mut self, frame: &Canvas) -> Vec<CellRun> {
+        let (text_buf, color_buf) = (frame.buffer(), frame.color_buffer());
+        let mut runs = Vec::new();
+        for y in 0..SCREEN_HEIGHT {
+            let same = |x: usize| self.last_frame.as_ref()
+                .map(|last_frame| {
+                    last_frame.buffer()[y][x] == text_buf[y][x]
+                        && last_frame.color_buffer()[y][x] == color_buf[y][x]
+                }).unwrap_or(false);
+            let mut x = 0;
+            while x < SCREEN_WIDTH {
+                if same(x) {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < SCREEN_WIDTH && !same(x) {
+                    x += 1;
+                }
+                runs.push(CellRun {
+                    x: start,
+                    y,
+                    text: text_buf[y][start..x].iter().collect(),
+                    colors: color_buf[y][start..x].to_vec()
+                });
+            }
+        }
+        self.last_frame = Some(frame.clone());
+        runs
+    }
+}
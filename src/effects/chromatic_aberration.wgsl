@@ -0,0 +1,9 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+// Description: Split red and blue apart toward the edges of the frame like a cheap lens.
+// params.x is how many pixels apart they are at the corners
+
+fn effect(uv: vec2<f32>) -> vec4<f32> {
+    let offset = (uv - 0.5) * 2.0 * post.params.x / post.resolution;
+    let color = sample_frame(uv);
+    return vec4<f32>(sample_frame(uv + offset).r, color.g, sample_frame(uv - offset).b, color.a);
+}
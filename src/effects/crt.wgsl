@@ -0,0 +1,12 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+// Description: Bend the frame like the glass of a CRT. params.x is how far the edges curve
+
+fn effect(uv: vec2<f32>) -> vec4<f32> {
+    let centered = uv * 2.0 - 1.0;
+    let curved = centered * (1.0 + post.params.x * centered.yx * centered.yx);
+    let bent_uv = curved * 0.5 + 0.5;
+    if any(bent_uv < vec2<f32>(0.0)) || any(bent_uv > vec2<f32>(1.0)) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    return sample_frame(bent_uv);
+}
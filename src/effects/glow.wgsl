@@ -0,0 +1,18 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+// Description: Bleed light into the area around bright text like phosphor.
+// params.x is how bright the glow is, params.y how far it reaches in pixels
+
+fn effect(uv: vec2<f32>) -> vec4<f32> {
+    let color = sample_frame(uv);
+    let texel = post.params.y / post.resolution;
+    var glow = vec3<f32>(0.0);
+    // Two rings of eight samples, the outer one counting for less
+    for (var i = 0; i < 8; i++) {
+        let angle = f32(i) * 0.7853982;
+        let dir = vec2<f32>(cos(angle), sin(angle));
+        glow += sample_frame(uv + dir * texel * 0.5).rgb * 2.0;
+        glow += sample_frame(uv + dir * texel).rgb;
+    }
+    glow /= 24.0;
+    return vec4<f32>(color.rgb + glow * post.params.x, color.a);
+}
@@ -0,0 +1,8 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+// Description: Darken lines across the frame. params.x is how dark, params.y how many lines
+
+fn effect(uv: vec2<f32>) -> vec4<f32> {
+    let color = sample_frame(uv);
+    let line = 0.5 + 0.5 * cos(uv.y * post.params.y * 6.2831853);
+    return vec4<f32>(color.rgb * (1.0 - post.params.x * line), color.a);
+}
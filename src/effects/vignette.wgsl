@@ -0,0 +1,10 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+// Description: Darken the frame toward its corners. params.x is how dark the corners get,
+// params.y how far from the center it starts, with 0.7 being about the corners
+
+fn effect(uv: vec2<f32>) -> vec4<f32> {
+    let color = sample_frame(uv);
+    let dist = distance(uv, vec2<f32>(0.5));
+    let shade = smoothstep(post.params.y, 0.75, dist) * post.params.x;
+    return vec4<f32>(color.rgb * (1.0 - shade), color.a);
+}
@@ -5,314 +5,169 @@ use std::{
     time::{
         SystemTime, Duration, Instant
     }, collections::HashMap,
-    borrow::Cow
+    fs
 };
 use wgpu::{
-    Instance, RequestAdapterOptions, PowerPreference, Device, Surface, SurfaceConfiguration,
-    Queue, Features, Limits, DeviceDescriptor, TextureViewDescriptor,
-    CommandEncoderDescriptor, RenderPassDescriptor, RenderPassColorAttachment, Operations,
-    LoadOp, Color, ShaderSource, ShaderModuleDescriptor, PipelineLayoutDescriptor,
-    RenderPipelineDescriptor, RenderPipeline
-};
-use wgpu_text::{
-    BrushBuilder,
-    glyph_brush::{
-        VerticalAlign, BuiltInLineBreaker, Layout, Section, Text
-    }
+    Instance, Surface, TextureUsages, TextureViewDescriptor
 };
 use winit::{
     event_loop::{
         EventLoop, ControlFlow
-    }, window::{
-        Window, Theme
-    }, event::{
-        Event, WindowEvent, KeyboardInput, ElementState
-    }, dpi::PhysicalSize,
+    },
+    window::Window,
+    event::{
+        Event, WindowEvent, KeyboardInput, ElementState, VirtualKeyCode, MouseScrollDelta
+    }
 };
-use crate::obj::GameObject;
-
-/// The terminal will appear to be 80x25, but this is the size of the window surrounding that
-const WINDOW_SIZE: PhysicalSize<u32> = PhysicalSize { width: 1024, height: 576 };
-const TEXT_OFFSET: (f32, f32) = (85.0, WINDOW_SIZE.height as f32 * 0.5);
-const FONT_SIZE: f32 = 22.0;
-const FRAME_RATE: f64 = 60.0;
-const BASE_BUFF: [[char; 81]; 25] = [
-    [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\n'
-    ], [
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ', ' ',
-        '\0'
-    ]
-];
+use crate::{
+    obj::{
+        GameObject, MouseEvent
+    },
+    art::StaticArt,
+    camera::Viewport,
+    cmd::{
+        self, Command
+    }, game::{
+        Game, RoomSettings
+    },
+    headless::HeadlessEnvironment,
+    cast::{
+        self, CastRecorder
+    }, video::{
+        self, VideoRecorder
+    }, render::{
+        self, Renderer, OffscreenTarget, WINDOW_SIZE
+    }, font::FontChain,
+    diff::FrameDiff,
+    post::Effect,
+    screenshot,
+    theme::Theme,
+    tileset::Tileset,
+    transition::Transition
+};
+#[cfg(feature = "serde")]
+use crate::{
+    save::{
+        Snapshot, Registry
+    }, room::RoomDef
+};
+
+pub(crate) const FRAME_RATE: f64 = 60.0;
 
 /// Core engine. Create game objs & rooms via builder then run with this immutably.
 pub struct Environment {
-    global_game_objs: Vec<Box<dyn GameObject>>,
-    rooms: HashMap<String, Vec<Box<dyn GameObject>>>,
-    start_room: String,
+    game: Game,
     win_title: String,
+    screenshot_key: Option<VirtualKeyCode>,
+    cast_key: Option<VirtualKeyCode>,
+    cast_path: Option<String>,
+    video_key: Option<VirtualKeyCode>,
+    video_path: Option<String>,
+    skip_unchanged_frames: bool,
+    theme: Theme,
+    #[cfg(feature = "serde")]
+    saves: SaveSettings,
 
     ev_loop: EventLoop<()>,
     window: Window,
 
     surface: Surface,
-    device: Device,
-    queue: Queue,
-    config: SurfaceConfiguration,
-    render_pipeline: RenderPipeline,
-    font: &'static [u8]
+    renderer: Renderer
 }
 
 impl Environment {
     /// Create a window and run the game from established code
     pub async fn run(self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut global_objs = self.global_game_objs.clone();
-        let mut rooms = self.rooms.clone();
-        let mut cur_room = self.start_room.clone();
-        let mut brush = BrushBuilder::using_font_bytes(self.font)
-            .expect("Failed to create text brush.")
-            .build(
-                &self.device, WINDOW_SIZE.width, WINDOW_SIZE.height,
-                self.config.format
-            );
+        let mut game = self.game.clone();
+        let mut renderer = self.renderer;
         let mut then = SystemTime::now();
         let mut now = SystemTime::now();
         let mut fps = 0;
         let target_framerate = Duration::from_secs_f64(1.0 / FRAME_RATE);
         let mut delta_time = Instant::now();
+        let mut screenshot_path: Option<String> = None;
+        let mut theme = self.theme.clone();
+        let mut cast_recorder = match &self.cast_path {
+            Some(path) => Some((
+                CastRecorder::create(path, &self.win_title, &theme)?,
+                Instant::now()
+            )), None => None
+        };
+        let mut video_recorder = match &self.video_path {
+            Some(path) => Some((
+                VideoRecorder::create(path, WINDOW_SIZE.width, WINDOW_SIZE.height)?,
+                Instant::now()
+            )), None => None
+        };
+        let offscreen = OffscreenTarget::new(&renderer.device, renderer.format);
+        let mut frame_diff = FrameDiff::new();
+        // Redraws we didn't ask for come from the system, like when the window is uncovered
+        let mut frame_due = false;
+        let mut mouse_cell = None;
         self.ev_loop.run(move |ev, _, ctl_flow| {
             *ctl_flow = ControlFlow::Poll;
             match ev {
-                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } =>
-                    *ctl_flow = ControlFlow::Exit,
-                Event::RedrawRequested(_) => {
-                    // Build the text buffer
-                    let mut text_buf = BASE_BUFF.clone();
-                    for obj in global_objs.iter() {
-                        obj.draw(&mut text_buf);
+                Event::WindowEvent { event: WindowEvent::CloseRequested, .. } => {
+                    stop_cast(&mut cast_recorder);
+                    stop_video(&mut video_recorder);
+                    *ctl_flow = ControlFlow::Exit;
+                }, Event::RedrawRequested(_) => {
+                    let canvas = game.draw(&theme);
+                    if let Some((cast, start)) = &mut cast_recorder {
+                        if let Err(err) = cast.record(start.elapsed().as_secs_f64(), &canvas) {
+                            eprintln!("Failed to record frame, stopping recording: {}", err);
+                            cast_recorder = None;
+                        }
                     }
-                    for obj in rooms[&cur_room].iter() {
-                        obj.draw(&mut text_buf);
+
+                    // Video frames are drawn offscreen so they can be read back from the gpu
+                    if let Some((video, start)) = &mut video_recorder {
+                        let time = start.elapsed().as_secs_f64();
+                        if video.wants_frame(time) {
+                            let frame = renderer.render(&canvas, &offscreen.view)
+                                .and_then(|_| render::read_rgba(
+                                    &renderer.device, &renderer.queue, &offscreen.texture
+                                )).and_then(|pixels| video.record(time, pixels));
+                            if let Err(err) = frame {
+                                eprintln!("Failed to record frame, stopping recording: {}", err);
+                                video_recorder = None;
+                            }
+                        }
                     }
 
-                    // Apply it to screen
-                    let mut buff_strs = text_buf.iter()
-                        .map(|line| line.iter().collect::<String>())
-                        .collect::<Vec<String>>();
-                    let buf_str = buff_strs.iter_mut().reduce(|final_str, line| {
-                        final_str.push_str(line.as_str());
-                        final_str
-                    }).unwrap();
-                    let section = Section::default()
-                        .add_text(
-                            Text::new(buf_str.as_str())
-                                .with_scale(FONT_SIZE)
-                                .with_color([ 0.8, 0.85, 0.9, 1.0 ])
-                        ).with_bounds((WINDOW_SIZE.width as f32, WINDOW_SIZE.height as f32))
-                        .with_layout(
-                            Layout::default()
-                                .v_align(VerticalAlign::Center)
-                                .line_breaker(BuiltInLineBreaker::AnyCharLineBreaker)
-                        ).with_screen_position(TEXT_OFFSET)
-                        .to_owned();
-                    brush.queue(&self.device, &self.queue, vec![ &section ])
-                        .expect("Failed to draw text.");
-                    let frame = self.surface.get_current_texture()
-                        .expect("Failed to acquire next swap chain texture.");
-                    let view = frame.texture.create_view(&TextureViewDescriptor::default());
-                    let mut encoder = self.device.create_command_encoder(
-                        &CommandEncoderDescriptor { label: None }
-                    );
-                    {
-                        let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
-                            label: None,
-                            color_attachments: &[Some(RenderPassColorAttachment {
-                                view: &view,
-                                resolve_target: None,
-                                ops: Operations {
-                                    load: LoadOp::Clear(Color::BLACK),
-                                    store: true
-                                }
-                            })], depth_stencil_attachment: None
-                        });
-                        rpass.set_pipeline(&self.render_pipeline);
-                        rpass.draw(0..6, 0..1);
-                        brush.draw(&mut rpass);
+                    // Apply it to screen, unless it would look the same as what's there
+                    let asked_for = std::mem::take(&mut frame_due);
+                    let changed = frame_diff.changed(&canvas);
+                    if changed || !asked_for || !self.skip_unchanged_frames
+                            || screenshot_path.is_some() || renderer.has_effects() {
+                        let frame = self.surface.get_current_texture()
+                            .expect("Failed to acquire next swap chain texture.");
+                        let view = frame.texture.create_view(&TextureViewDescriptor::default());
+                        renderer.render(&canvas, &view).expect("Failed to draw text.");
+                        if let Some(path) = screenshot_path.take() {
+                            // Not every surface can be copied from, so draw offscreen for those
+                            let image = if frame.texture.usage().contains(TextureUsages::COPY_SRC) {
+                                Ok(&frame.texture)
+                            } else {
+                                renderer.render(&canvas, &offscreen.view)
+                                    .map(|_| &offscreen.texture)
+                            };
+                            // A failed screenshot shouldn't take the whole game down with it
+                            if let Err(err) = image.and_then(|image| screenshot::save_png(
+                                &renderer.device, &renderer.queue, image, &path
+                            )) {
+                                eprintln!("Failed to save screenshot image: {}", err);
+                            }
+                            if let Err(err) = screenshot::save_text(
+                                &canvas, &theme, &path
+                            ) {
+                                eprintln!("Failed to save screenshot text: {}", err);
+                            }
+                        }
+                        frame.present();
+                        fps += 1;
                     }
-                    self.queue.submit(Some(encoder.finish()));
-                    frame.present();
 
-                    fps += 1;
                     if now.duration_since(then).unwrap().as_millis() > 1000 {
                         self.window.set_title(&format!("{}, FPS: {}", self.win_title, fps));
                         fps = 0;
@@ -325,70 +180,140 @@ impl Environment {
                     if virtual_keycode.is_none() {
                         return;
                     }
-                    let globals_clone = global_objs.clone();
-                    let rooms_clone = rooms.clone();
-                    let mut room = rooms[&cur_room].clone();
-                    match state {
-                        ElementState::Pressed => {
-                            for obj in global_objs.iter_mut() {
-                                obj.on_key_pressed(
-                                    virtual_keycode.unwrap(),
-                                    &globals_clone, &rooms_clone, &mut cur_room
-                                );
-                            }
-                            for obj in room.iter_mut() {
-                                obj.on_key_pressed(
-                                    virtual_keycode.unwrap(),
-                                    &globals_clone, &rooms_clone, &mut cur_room
-                                );
-                            }
-                        }, ElementState::Released => {
-                            for obj in global_objs.iter_mut() {
-                                obj.on_key_released(
-                                    virtual_keycode.unwrap(),
-                                    &globals_clone, &rooms_clone, &mut cur_room
-                                );
-                            }
-                            for obj in room.iter_mut() {
-                                obj.on_key_released(
-                                    virtual_keycode.unwrap(),
-                                    &globals_clone, &rooms_clone, &mut cur_room
-                                );
-                            } 
-                        }
+                    if state == ElementState::Pressed && virtual_keycode == self.screenshot_key {
+                        cmd::send(Command::Screenshot(screenshot::default_path()));
+                        return;
                     }
-                    rooms.insert(cur_room.clone(), room);
-                }, Event::MainEventsCleared => {
-                    let globals_clone = global_objs.clone();
-                    let rooms_clone = rooms.clone();
-                    let mut room = rooms[&cur_room].clone();
-                    let old_cur_room = cur_room.clone();
-                    for obj in global_objs.iter_mut() {
-                        obj.update(
-                            delta_time.elapsed().as_secs_f32(),
-                            &globals_clone, &rooms_clone, &mut cur_room
-                        );
+                    if state == ElementState::Pressed && virtual_keycode == self.cast_key {
+                        cmd::send(match cast_recorder {
+                            Some(_) => Command::StopCast,
+                            None => Command::StartCast(cast::default_path())
+                        });
+                        return;
                     }
-                    for obj in room.iter_mut() {
-                        obj.update(
-                            delta_time.elapsed().as_secs_f32(),
-                            &globals_clone, &rooms_clone, &mut cur_room
-                        );
+                    if state == ElementState::Pressed && virtual_keycode == self.video_key {
+                        cmd::send(match video_recorder {
+                            Some(_) => Command::StopVideo,
+                            None => Command::StartVideo(video::default_path())
+                        });
+                        return;
                     }
-                    if cur_room != old_cur_room {
-                        // Reset unless persistent on room change
-                        let new_rooms = self.rooms.clone();
-                        let new_room = new_rooms.get(&old_cur_room).unwrap();
-                        for i in 0..room.len() {
-                            if !room[i].persistent() {
-                                room[i] = new_room[i].clone();
+                    #[cfg(feature = "serde")]
+                    if state == ElementState::Pressed
+                            && virtual_keycode == self.saves.quicksave_key {
+                        cmd::send(Command::Save(self.saves.quicksave_path.clone()));
+                        return;
+                    }
+                    #[cfg(feature = "serde")]
+                    if state == ElementState::Pressed
+                            && virtual_keycode == self.saves.quickload_key {
+                        cmd::send(Command::Load(self.saves.quicksave_path.clone()));
+                        return;
+                    }
+                    game.key_event(virtual_keycode.unwrap(), state);
+                }, Event::WindowEvent {
+                    event: WindowEvent::ReceivedCharacter(c), ..
+                } if !c.is_control() => {
+                    // Keys like backspace come through as characters too, but they're keys
+                    game.char_typed(c);
+                }, Event::WindowEvent { event: WindowEvent::CursorMoved { position, .. }, .. } => {
+                    mouse_cell = renderer.cell_at((position.x, position.y));
+                    if let Some(cell) = mouse_cell {
+                        game.mouse_event(MouseEvent::Moved(cell));
+                    }
+                }, Event::WindowEvent { event: WindowEvent::MouseInput {
+                    state, button, ..
+                }, .. } => if let Some(cell) = mouse_cell {
+                    game.mouse_event(match state {
+                        ElementState::Pressed => MouseEvent::Pressed(button, cell),
+                        ElementState::Released => MouseEvent::Released(button, cell)
+                    });
+                }, Event::WindowEvent { event: WindowEvent::MouseWheel { delta, .. }, .. } => {
+                    let lines = match delta {
+                        MouseScrollDelta::LineDelta(_, lines) => lines.round() as i32,
+                        MouseScrollDelta::PixelDelta(pixels) => pixels.y.signum() as i32
+                    };
+                    if let (Some(cell), true) = (mouse_cell, lines != 0) {
+                        game.mouse_event(MouseEvent::Scrolled(lines, cell));
+                    }
+                }, Event::MainEventsCleared => {
+                    game.update(delta_time.elapsed().as_secs_f32());
+
+                    for command in game.drain_commands() {
+                        match command {
+                            Command::Screenshot(path) => screenshot_path = Some(path),
+                            Command::StartCast(path) => {
+                                stop_cast(&mut cast_recorder);
+                                cast_recorder = match CastRecorder::create(
+                                    &path, &self.win_title, &theme
+                                ) {
+                                    Ok(cast) => Some((cast, Instant::now())),
+                                    Err(err) => {
+                                        eprintln!("Failed to start recording: {}", err);
+                                        None
+                                    }
+                                };
+                            }, Command::StopCast => stop_cast(&mut cast_recorder),
+                            Command::StartVideo(path) => {
+                                stop_video(&mut video_recorder);
+                                video_recorder = match VideoRecorder::create(
+                                    &path, WINDOW_SIZE.width, WINDOW_SIZE.height
+                                ) {
+                                    Ok(video) => Some((video, Instant::now())),
+                                    Err(err) => {
+                                        eprintln!("Failed to start recording: {}", err);
+                                        None
+                                    }
+                                };
+                            }, Command::StopVideo => stop_video(&mut video_recorder),
+                            Command::MoveCamera(viewport, x, y) =>
+                                game.move_camera(viewport, (x, y)),
+                            Command::ShakeCamera(magnitude, duration) =>
+                                game.shake_cameras(magnitude, duration),
+                            Command::SetTheme(new_theme) => set_theme(
+                                new_theme, &mut theme,
+                                &mut renderer, &mut frame_diff, &mut cast_recorder
+                            ), Command::SetPalette(palette) => set_theme(
+                                theme.set_palette(&palette), &mut theme,
+                                &mut renderer, &mut frame_diff, &mut cast_recorder
+                            ), Command::SetPaletteColor(index, color) => set_theme(
+                                theme.set_palette_color(index, color), &mut theme,
+                                &mut renderer, &mut frame_diff, &mut cast_recorder
+                            ), Command::SetEffectParams(name, params) => {
+                                if let Err(err) = renderer.set_effect_params(&name, params) {
+                                    eprintln!("Failed to change effect: {}", err);
+                                }
+                                frame_diff.reset();
+                            }, Command::SetEffectEnabled(name, enabled) => {
+                                if let Err(err) = renderer.set_effect_enabled(&name, enabled) {
+                                    eprintln!("Failed to change effect: {}", err);
+                                }
+                                frame_diff.reset();
+                            }, Command::StartTimer(timer) => game.start_timer(timer),
+                            Command::CancelTimer(name) => game.cancel_timer(&name),
+                            Command::StartTween(name, target, tween) =>
+                                game.start_tween(&name, target, tween),
+                            Command::StopTween(name) => game.stop_tween(&name),
+                            Command::SetTimeScale(time_scale) => game.set_time_scale(time_scale),
+                            Command::SetPaused(paused) => game.set_paused(paused),
+                            Command::Message(channel, text) => game.message(&channel, &text),
+                            #[cfg(feature = "serde")]
+                            Command::Save(path) => if let Err(err) = game.snapshot().save(&path) {
+                                eprintln!("Failed to save game: {}", err);
+                            },
+                            #[cfg(feature = "serde")]
+                            Command::Load(path) => if let Err(err) = Snapshot::load(&path)
+                                    .and_then(|snapshot| {
+                                        game.restore(&snapshot, &self.saves.registry)
+                                    }) {
+                                eprintln!("Failed to load game: {}", err);
                             }
                         }
                     }
-                    rooms.insert(cur_room.clone(), room);
 
                     if target_framerate <= delta_time.elapsed() {
                         self.window.request_redraw();
+                        frame_due = true;
                         delta_time = Instant::now();
                     } else {
                         *ctl_flow = ControlFlow::WaitUntil(
@@ -402,13 +327,69 @@ impl Environment {
     }
 }
 
+/// Recolor everything from the next frame on, including any recording in progress
+fn set_theme(
+        new_theme: Theme, theme: &mut Theme, renderer: &mut Renderer,
+        frame_diff: &mut FrameDiff, cast_recorder: &mut Option<(CastRecorder, Instant)>) {
+    renderer.set_theme(&new_theme);
+    frame_diff.reset();
+    if let Some((cast, _)) = cast_recorder {
+        cast.set_theme(&new_theme);
+    }
+    *theme = new_theme;
+}
+
+/// Finish off the windowed asciinema recording in progress, if any
+fn stop_cast(recorder: &mut Option<(CastRecorder, Instant)>) {
+    if let Some((cast, start)) = recorder.take() {
+        if let Err(err) = cast.finish(start.elapsed().as_secs_f64()) {
+            eprintln!("Failed to finish recording: {}", err);
+        }
+    }
+}
+
+/// Finish off the windowed video recording in progress, if any
+fn stop_video(recorder: &mut Option<(VideoRecorder, Instant)>) {
+    if let Some((video, start)) = recorder.take() {
+        if let Err(err) = video.finish(start.elapsed().as_secs_f64()) {
+            eprintln!("Failed to finish recording: {}", err);
+        }
+    }
+}
+
+/// How games get saved and loaded, along with the types that can be loaded
+#[cfg(feature = "serde")]
+#[derive(Clone)]
+struct SaveSettings {
+    registry: Registry,
+    quicksave_path: String,
+    quicksave_key: Option<VirtualKeyCode>,
+    quickload_key: Option<VirtualKeyCode>
+}
+
 /// Builder for the game environment. Create rooms w/ objs and add them here, then build and run
 #[derive(Clone)]
 pub struct EnvironmentBuilder {
     global_game_objs: Vec<Box<dyn GameObject>>,
     rooms: HashMap<String, Vec<Box<dyn GameObject>>>,
+    room_settings: HashMap<String, RoomSettings>,
+    viewports: Vec<Viewport>,
     start_room: String,
-    win_title: String
+    win_title: String,
+    screenshot_key: Option<VirtualKeyCode>,
+    cast_key: Option<VirtualKeyCode>,
+    cast_path: Option<String>,
+    video_key: Option<VirtualKeyCode>,
+    video_path: Option<String>,
+    headless_rendering: bool,
+    skip_unchanged_frames: bool,
+    fonts: FontChain,
+    tileset: Option<Tileset>,
+    effects: Vec<Effect>,
+    theme: Theme,
+    transition: Option<Transition>,
+    #[cfg(feature = "serde")]
+    saves: SaveSettings
 }
 
 impl EnvironmentBuilder {
@@ -416,18 +397,170 @@ impl EnvironmentBuilder {
         Self {
             global_game_objs: Vec::new(),
             rooms: HashMap::new(),
+            room_settings: HashMap::new(),
+            viewports: Vec::new(),
             start_room: start_room.to_string(),
-            win_title: "Pseudo-Term Window".to_string()
+            win_title: "Pseudo-Term Window".to_string(),
+            screenshot_key: Some(VirtualKeyCode::F12),
+            cast_key: Some(VirtualKeyCode::F10),
+            cast_path: None,
+            video_key: Some(VirtualKeyCode::F9),
+            video_path: None,
+            headless_rendering: false,
+            skip_unchanged_frames: false,
+            fonts: FontChain::default(),
+            tileset: None,
+            effects: Vec::new(),
+            theme: Theme::default(),
+            transition: None,
+            #[cfg(feature = "serde")]
+            saves: SaveSettings {
+                registry: Registry::new(),
+                quicksave_path: "quicksave.ron".to_string(),
+                quicksave_key: Some(VirtualKeyCode::F5),
+                quickload_key: Some(VirtualKeyCode::F8)
+            }
         }
     }
 
     pub fn set_window_title(&self, win_title: &str) -> Self {
-        Self {
-            global_game_objs: self.global_game_objs.clone(),
-            rooms: self.rooms.clone(),
-            start_room: self.start_room.clone(),
-            win_title: win_title.to_string()
+        let mut new = self.clone();
+        new.win_title = win_title.to_string();
+        new
+    }
+
+    /// Key that saves a screenshot (F12 by default). None to disable it
+    pub fn set_screenshot_key(&self, key: Option<VirtualKeyCode>) -> Self {
+        let mut new = self.clone();
+        new.screenshot_key = key;
+        new
+    }
+
+    /// Key that starts and stops an asciinema recording (F10 by default). None to disable it
+    pub fn set_cast_key(&self, key: Option<VirtualKeyCode>) -> Self {
+        let mut new = self.clone();
+        new.cast_key = key;
+        new
+    }
+
+    /// Start recording to an asciinema .cast file as soon as the game runs
+    pub fn record_cast(&self, path: &str) -> Self {
+        let mut new = self.clone();
+        new.cast_path = Some(path.to_string());
+        new
+    }
+
+    /// Key that starts and stops a GIF recording (F9 by default). None to disable it
+    pub fn set_video_key(&self, key: Option<VirtualKeyCode>) -> Self {
+        let mut new = self.clone();
+        new.video_key = key;
+        new
+    }
+
+    /// Start recording to a .gif or .png (APNG) file as soon as the game runs
+    pub fn record_video(&self, path: &str) -> Self {
+        let mut new = self.clone();
+        new.video_path = Some(path.to_string());
+        new
+    }
+
+    /// Render frames offscreen in headless environments, so they can be recorded to video.
+    /// Uses a software adapter if there's no gpu available
+    pub fn set_headless_rendering(&self, headless_rendering: bool) -> Self {
+        let mut new = self.clone();
+        new.headless_rendering = headless_rendering;
+        new
+    }
+
+    /// Don't draw or present frames where nothing on the grid changed, saving power in games
+    /// that sit still. Windows are still redrawn whenever the system asks
+    pub fn set_skip_unchanged_frames(&self, skip_unchanged_frames: bool) -> Self {
+        let mut new = self.clone();
+        new.skip_unchanged_frames = skip_unchanged_frames;
+        new
+    }
+
+    /// Draw the grid in a font other than the built-in one, from .ttf or .otf data.
+    /// Cells are sized to fit it, so it should be monospaced. Size must be positive
+    pub fn set_font(&self, bytes: &[u8], size: f32) -> Result<Self, Box<dyn std::error::Error>> {
+        if !(size.is_finite() && size > 0.0) {
+            Err("Font size must be a positive number.")?;
         }
+        let mut new = self.clone();
+        new.fonts.set_primary(FontChain::parse(bytes)?, size);
+        Ok(new)
+    }
+
+    /// Same as set_font, reading the font from a file
+    pub fn load_font(&self, path: &str, size: f32) -> Result<Self, Box<dyn std::error::Error>> {
+        self.set_font(&fs::read(path)?, size)
+    }
+
+    /// Add a font to draw characters the main font is missing, like emoji or CJK.
+    /// Fallbacks are tried in the order they're added
+    pub fn add_fallback_font(&self, bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut new = self.clone();
+        new.fonts.add_fallback(FontChain::parse(bytes)?);
+        Ok(new)
+    }
+
+    /// Same as add_fallback_font, reading the font from a file
+    pub fn load_fallback_font(&self, path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        self.add_fallback_font(&fs::read(path)?)
+    }
+
+    /// Draw the grid with a CP437 tile sheet instead of fonts, or go back to fonts with None
+    pub fn set_tileset(&self, tileset: Option<&Tileset>) -> Self {
+        let mut new = self.clone();
+        new.tileset = tileset.cloned();
+        new
+    }
+
+    /// Draw the grid with a CP437 tile sheet read from a PNG file
+    pub fn load_tileset(&self, path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(self.set_tileset(Some(&Tileset::load(path)?)))
+    }
+
+    /// Run every frame through a post-processing effect, after any added before it.
+    /// Effects also show up in screenshots and video
+    pub fn add_effect(&self, effect: &Effect) -> Self {
+        let mut new = self.clone();
+        new.effects.push(effect.clone());
+        new
+    }
+
+    /// Colors of the terminal, its border and the window around it. Games can switch themes
+    /// while running with Command::SetTheme
+    pub fn set_theme(&self, theme: &Theme) -> Self {
+        let mut new = self.clone();
+        new.theme = theme.clone();
+        new
+    }
+
+    /// Types of game objects that can be recreated when loading a save
+    #[cfg(feature = "serde")]
+    pub fn set_registry(&self, registry: &Registry) -> Self {
+        let mut new = self.clone();
+        new.saves.registry = registry.clone();
+        new
+    }
+
+    /// File that quicksaves go to. Format is picked by extension (quicksave.ron by default)
+    #[cfg(feature = "serde")]
+    pub fn set_quicksave_path(&self, path: &str) -> Self {
+        let mut new = self.clone();
+        new.saves.quicksave_path = path.to_string();
+        new
+    }
+
+    /// Keys that quicksave and quickload (F5 and F8 by default). None to disable them
+    #[cfg(feature = "serde")]
+    pub fn set_quicksave_keys(
+            &self, save_key: Option<VirtualKeyCode>, load_key: Option<VirtualKeyCode>) -> Self {
+        let mut new = self.clone();
+        new.saves.quicksave_key = save_key;
+        new.saves.quickload_key = load_key;
+        new
     }
 
     pub fn add_global_obj(&self, obj: Box<dyn GameObject>) -> Self {
@@ -436,12 +569,112 @@ impl EnvironmentBuilder {
         new
     }
 
-    pub fn add_room(&self, name: &str, room: &Vec<Box<dyn GameObject>>) -> Self {
+    pub fn add_room(&self, name: &str, room: &[Box<dyn GameObject>]) -> Self {
         let mut new = self.clone();
-        new.rooms.insert(name.to_string(), room.clone());
+        new.rooms.insert(name.to_string(), room.to_vec());
         new
     }
 
+    /// Art drawn behind everything in a room, before any objects
+    pub fn set_room_background(&self, name: &str, background: &StaticArt) -> Self {
+        let mut new = self.clone();
+        new.room_settings.entry(name.to_string()).or_default().background =
+            Some(background.clone());
+        new
+    }
+
+    /// Art drawn over everything in a room, after all objects
+    pub fn set_room_foreground(&self, name: &str, foreground: &StaticArt) -> Self {
+        let mut new = self.clone();
+        new.room_settings.entry(name.to_string()).or_default().foreground =
+            Some(foreground.clone());
+        new
+    }
+
+    /// World size of a room that's bigger than the screen, for the camera to move around in
+    pub fn set_room_size(&self, name: &str, width: usize, height: usize) -> Self {
+        let mut new = self.clone();
+        new.room_settings.entry(name.to_string()).or_default().size = Some((width, height));
+        new
+    }
+
+    /// Play a transition on every room switch, except into rooms with their own.
+    /// Without one, rooms switch instantly
+    pub fn set_transition(&self, transition: Option<&Transition>) -> Self {
+        let mut new = self.clone();
+        new.transition = transition.copied();
+        new
+    }
+
+    /// Play a transition when switching into one room
+    pub fn set_room_transition(&self, name: &str, transition: &Transition) -> Self {
+        let mut new = self.clone();
+        new.room_settings.entry(name.to_string()).or_default().transition = Some(*transition);
+        new
+    }
+
+    /// Split the screen up for every room that doesn't have viewports of its own.
+    /// Without any, the whole screen is one viewport
+    pub fn add_viewport(&self, viewport: &Viewport) -> Self {
+        let mut new = self.clone();
+        new.viewports.push(viewport.clone());
+        new
+    }
+
+    /// Split the screen up differently in one room
+    pub fn add_room_viewport(&self, name: &str, viewport: &Viewport) -> Self {
+        let mut new = self.clone();
+        new.room_settings.entry(name.to_string()).or_default().viewports.push(viewport.clone());
+        new
+    }
+
+    /// Add a room from a .ron, .toml or .json file. Object types come from the registry,
+    /// so set that first
+    #[cfg(feature = "serde")]
+    pub fn load_room(&self, path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let room = RoomDef::load(path)?;
+        let name = room.name.clone().ok_or("Room file has no name.")?;
+        let mut new = self.add_room(&name, &room.build(&self.saves.registry)?);
+        if let Some(background) = room.background_layer()? {
+            new = new.set_room_background(&name, &background);
+        }
+        if let Some(foreground) = room.foreground_layer()? {
+            new = new.set_room_foreground(&name, &foreground);
+        }
+        if let Some((width, height)) = room.size {
+            new = new.set_room_size(&name, width, height);
+        }
+        for viewport in room.viewports.iter() {
+            new = new.add_room_viewport(&name, &viewport.to_viewport());
+        }
+        Ok(new)
+    }
+
+    /// Create an environment without a window that's stepped manually at a fixed frame rate
+    pub async fn build_headless(&self) -> Result<HeadlessEnvironment, Box<dyn std::error::Error>> {
+        let renderer = if self.headless_rendering || self.video_path.is_some() {
+            Some(OffscreenTarget::new_with_renderer(
+                &self.fonts, self.tileset.as_ref(), &self.effects, &self.theme
+            ).await?)
+        } else {
+            None
+        };
+        #[allow(unused_mut)]
+        let mut env = HeadlessEnvironment::new(
+            Game::new(
+                &self.global_game_objs, &self.rooms,
+                &self.room_settings, &self.viewports, self.transition, &self.start_room
+            ),
+            &self.win_title, &self.theme,
+            self.cast_path.as_deref(), self.video_path.as_deref(), renderer
+        )?;
+        #[cfg(feature = "serde")]
+        {
+            env.registry = self.saves.registry.clone();
+        }
+        Ok(env)
+    }
+
     pub async fn build(&self) -> Result<Environment, Box<dyn std::error::Error>> {
         let ev_loop = EventLoop::new();
 
@@ -449,56 +682,21 @@ impl EnvironmentBuilder {
         window.set_inner_size(WINDOW_SIZE);
         window.set_resizable(false);
         window.set_title(&self.win_title);
-        window.set_theme(Some(Theme::Dark));
+        window.set_theme(Some(winit::window::Theme::Dark));
 
         let instance = Instance::default();
         let surface = unsafe { instance.create_surface(&window) }?;
-        let adapter = instance.request_adapter(&RequestAdapterOptions {
-            power_preference: PowerPreference::default(),
-            force_fallback_adapter: false,
-            compatible_surface: Some(&surface)
-        }).await;
-        if adapter.is_none() {
-            Err("Failed to find an appropriate adapter.")?;
-        }
-        let adapter = adapter.unwrap();
-        let (device, queue) = adapter.request_device(
-            &DeviceDescriptor {
-                label: None,
-                features: Features::empty(),
-                limits: Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits())
-            }, None
-        ).await?;
-
-        let bg_shader = device.create_shader_module(ShaderModuleDescriptor {
-            label: None,
-            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("bg_shader.wgsl"))),
-        });
-        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: None,
-            bind_group_layouts: &[],
-            push_constant_ranges: &[],
-        });
+        let (adapter, device, queue) = render::request_device(&instance, Some(&surface)).await?;
+
         let swapchain_capabilities = surface.get_capabilities(&adapter);
         let swapchain_format = swapchain_capabilities.formats[0];
-        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &bg_shader,
-                entry_point: "vs_main",
-                buffers: &[],
-            }, fragment: Some(wgpu::FragmentState {
-                module: &bg_shader,
-                entry_point: "fs_main",
-                targets: &[Some(swapchain_format.into())],
-            }), primitive: wgpu::PrimitiveState::default(),
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None
-        });
+        // Screenshots need to copy frames back out of the surface, if it'll let us
+        let mut usage = TextureUsages::RENDER_ATTACHMENT;
+        if swapchain_capabilities.usages.contains(TextureUsages::COPY_SRC) {
+            usage |= TextureUsages::COPY_SRC;
+        }
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: swapchain_format,
             width: WINDOW_SIZE.width,
             height: WINDOW_SIZE.height,
@@ -508,22 +706,31 @@ impl EnvironmentBuilder {
         };
         surface.configure(&device, &config);
 
-        let font: &[u8] = include_bytes!("font/OverpassMono-Regular.ttf");
+        let mut renderer = Renderer::new(
+            device, queue, swapchain_format, &self.fonts, self.tileset.as_ref()
+        )?;
+        renderer.set_effects(&self.effects).await?;
+        renderer.set_theme(&self.theme);
 
         Ok(Environment {
-            global_game_objs: self.global_game_objs.clone(),
-            rooms: self.rooms.clone(),
-            start_room: self.start_room.clone(),
+            game: Game::new(
+                &self.global_game_objs, &self.rooms,
+                &self.room_settings, &self.viewports, self.transition, &self.start_room
+            ),
             win_title: self.win_title.clone(),
+            screenshot_key: self.screenshot_key,
+            cast_key: self.cast_key,
+            cast_path: self.cast_path.clone(),
+            video_key: self.video_key,
+            video_path: self.video_path.clone(),
+            skip_unchanged_frames: self.skip_unchanged_frames,
+            theme: self.theme.clone(),
+            #[cfg(feature = "serde")]
+            saves: self.saves.clone(),
             ev_loop,
             window,
             surface,
-            device,
-            queue,
-            config,
-            render_pipeline,
-            font
+            renderer
         })
     }
 }
-
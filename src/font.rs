@@ -0,0 +1,204 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Fonts the grid is drawn in, rasterized a glyph at a time into an atlas of cell sized tiles
+
+use std::collections::HashMap;
+use ab_glyph::{
+    Font, FontArc, ScaleFont, point
+};
+
+const FONT_SIZE: f32 = 22.0;
+const FONT: &[u8] = include_bytes!("font/OverpassMono-Regular.ttf");
+/// Tiles across the glyph atlas. It grows downward as more glyphs are needed
+const ATLAS_COLUMNS: u32 = 16;
+/// Drawn in place of characters that come up after the atlas has grown as big as it can
+const FALLBACK_CHAR: char = '?';
+
+/// The font the grid is drawn in at some size, then fonts to take any glyphs it's missing from,
+/// in order. Cells are sized by the first font, so fallback glyphs are drawn into the same grid
+#[derive(Clone)]
+pub(crate) struct FontChain {
+    fonts: Vec<FontArc>,
+    size: f32
+}
+
+impl Default for FontChain {
+    fn default() -> Self {
+        Self {
+            fonts: vec![ FontArc::try_from_slice(FONT).expect("Built-in font is invalid.") ],
+            size: FONT_SIZE
+        }
+    }
+}
+
+impl FontChain {
+    pub fn parse(bytes: &[u8]) -> Result<FontArc, Box<dyn std::error::Error>> {
+        let font = FontArc::try_from_vec(bytes.to_vec())
+            .map_err(|_| "Font isn't valid .ttf or .otf data.")?;
+        Ok(font)
+    }
+
+    /// Replace the main font, keeping the fallbacks
+    pub fn set_primary(&mut self, font: FontArc, size: f32) {
+        self.fonts[0] = font;
+        self.size = size;
+    }
+
+    pub fn add_fallback(&mut self, font: FontArc) {
+        self.fonts.push(font);
+    }
+
+    /// Size of a cell in whole pixels, from the main font's 'M' and line height
+    pub fn cell_size(&self) -> (u32, u32) {
+        let font = self.fonts[0].as_scaled(self.size);
+        (
+            font.h_advance(font.glyph_id('M')).round().max(1.0) as u32,
+            (font.height() + font.line_gap()).round().max(1.0) as u32
+        )
+    }
+
+    /// The first font with a glyph for a character. The main font draws it if none do
+    fn font_for(&self, c: char) -> &FontArc {
+        self.fonts.iter().find(|font| font.glyph_id(c).0 != 0).unwrap_or(&self.fonts[0])
+    }
+}
+
+/// Every glyph drawn so far, each in its own cell sized tile of an RGBA atlas.
+/// Glyphs are white with coverage as alpha so cells can tint them
+pub(crate) struct GlyphCache {
+    fonts: FontChain,
+    tile_size: (u32, u32),
+    pixels: Vec<u8>,
+    rows: u32,
+    /// Most rows the gpu's largest texture can fit
+    max_rows: u32,
+    tiles: HashMap<char, u32>,
+    changed: bool
+}
+
+impl GlyphCache {
+    /// An atlas that can grow up to a texture size limit, like the device's
+    pub fn new(fonts: &FontChain, max_texture_size: u32) -> Self {
+        let tile_size = fonts.cell_size();
+        let max_rows = (max_texture_size / tile_size.1).max(1);
+        let rows = ATLAS_COLUMNS.min(max_rows);
+        let mut cache = Self {
+            fonts: fonts.clone(),
+            tile_size,
+            pixels: vec![0; (4 * ATLAS_COLUMNS * tile_size.0 * rows * tile_size.1) as usize],
+            rows,
+            max_rows,
+            tiles: HashMap::new(),
+            changed: true
+        };
+        cache.tile_for(FALLBACK_CHAR);
+        cache
+    }
+
+    pub fn tile_size(&self) -> (u32, u32) {
+        self.tile_size
+    }
+
+    /// Size of the atlas image in pixels
+    pub fn size(&self) -> (u32, u32) {
+        (ATLAS_COLUMNS * self.tile_size.0, self.rows * self.tile_size.1)
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Whether glyphs were added since the last time this was asked, so the atlas needs uploading
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// The tile that draws a character, rasterizing it the first time it's seen.
+    /// Once the atlas is full, new characters get the fallback's tile
+    pub fn tile_for(&mut self, c: char) -> u32 {
+        if let Some(tile) = self.tiles.get(&c) {
+            return *tile;
+        }
+        let tile = self.tiles.len() as u32;
+        if tile >= ATLAS_COLUMNS * self.rows {
+            if self.rows >= self.max_rows {
+                return self.tiles[&FALLBACK_CHAR];
+            }
+            // Rows go on the end, so tiles already drawn stay where they are
+            self.rows = (self.rows * 2).min(self.max_rows);
+            let (width, height) = self.size();
+            self.pixels.resize((4 * width * height) as usize, 0);
+        }
+        self.rasterize(c, tile);
+        self.tiles.insert(c, tile);
+        self.changed = true;
+        tile
+    }
+
+    fn rasterize(&mut self, c: char, tile: u32) {
+        if c.is_whitespace() || c.is_control() {
+            return;
+        }
+        let (tile_width, tile_height) = self.tile_size;
+        let font = self.fonts.font_for(c);
+        let id = font.glyph_id(c);
+
+        // Shrink glyphs too wide for a cell, like CJK from a fallback font.
+        // Every font shares the main font's baseline
+        let advance = font.as_scaled(self.fonts.size).h_advance(id);
+        let scale = if advance > tile_width as f32 {
+            self.fonts.size * tile_width as f32 / advance
+        } else {
+            self.fonts.size
+        };
+        let ascent = self.fonts.fonts[0].as_scaled(self.fonts.size).ascent();
+        let glyph = id.with_scale_and_position(scale, point(0.0, ascent));
+        let outline = match font.outline_glyph(glyph) {
+            Some(outline) => outline,
+            None => return
+        };
+
+        let bounds = outline.px_bounds();
+        let atlas_width = ATLAS_COLUMNS * tile_width;
+        let tile_pos = ((tile % ATLAS_COLUMNS) * tile_width, (tile / ATLAS_COLUMNS) * tile_height);
+        let pixels = &mut self.pixels;
+        outline.draw(|x, y, coverage| {
+            let x = bounds.min.x as i32 + x as i32;
+            let y = bounds.min.y as i32 + y as i32;
+            if (0..tile_width as i32).contains(&x) && (0..tile_height as i32).contains(&y) {
+                let (x, y) = (tile_pos.0 + x as u32, tile_pos.1 + y as u32);
+                let i = (4 * (y * atlas_width + x)) as usize;
+                let alpha = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+                pixels[i..i + 4].copy_from_slice(&[ 255, 255, 255, alpha ]);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_atlas_falls_back() {
+        let fonts = FontChain::default();
+        let mut cache = GlyphCache::new(&fonts, fonts.cell_size().1 * 2);
+        assert_eq!(cache.size().1, 2 * cache.tile_size().1);
+        assert_eq!(cache.tile_for(FALLBACK_CHAR), 0);
+        let tiles = ('a'..='z').chain('A'..='Z').map(|c| cache.tile_for(c)).collect::<Vec<_>>();
+        assert_eq!(tiles[..31], (1..32).collect::<Vec<_>>());
+        assert!(tiles[31..].iter().all(|tile| *tile == 0));
+        assert_eq!(cache.size().1, 2 * cache.tile_size().1);
+        assert_eq!(cache.tile_for('a'), 1);
+    }
+
+    #[test]
+    fn atlas_grows_by_rows() {
+        let mut cache = GlyphCache::new(&FontChain::default(), u32::MAX);
+        let rows = cache.size().1 / cache.tile_size().1;
+        for c in (0..ATLAS_COLUMNS * rows).filter_map(|i| char::from_u32(0x100 + i)) {
+            cache.tile_for(c);
+        }
+        assert_eq!(cache.size().1 / cache.tile_size().1, 2 * rows);
+        assert_eq!(cache.pixels().len() as u32, 4 * cache.size().0 * cache.size().1);
+    }
+}
@@ -0,0 +1,515 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! The state of a running game, shared between the windowed and headless environments
+
+use std::collections::HashMap;
+use winit::event::{
+    VirtualKeyCode, ElementState
+};
+use crate::{
+    cmd::{
+        Command, CommandQueue
+    }, obj::{
+        GameObject, MouseEvent
+    },
+    art::StaticArt,
+    collision,
+    canvas::{
+        Canvas, SCREEN_WIDTH, SCREEN_HEIGHT
+    }, camera::{
+        WorldBuffer, Viewport
+    }, theme::Theme,
+    timer::{
+        Timer, TimerAction, Timers
+    }, transition::{
+        Transition, ActiveTransition
+    }, tween::{
+        Tween, Tweens, TweenFrame
+    }
+};
+#[cfg(feature = "serde")]
+use crate::save::{
+    Snapshot, Registry
+};
+
+
+/// Everything about a room besides its objects
+#[derive(Clone, Default)]
+pub(crate) struct RoomSettings {
+    /// Art drawn behind and in front of the room's objects
+    pub background: Option<StaticArt>,
+    pub foreground: Option<StaticArt>,
+
+    /// World size, if the room is bigger than the screen
+    pub size: Option<(usize, usize)>,
+
+    /// How the screen is split up. Empty to use the game's default viewports
+    pub viewports: Vec<Viewport>,
+
+    /// Played when switching into the room, instead of the game's default transition
+    pub transition: Option<Transition>
+}
+
+/// Game objects and rooms, along with how to pass events to them and draw them
+#[derive(Clone)]
+pub(crate) struct Game {
+    pub global_objs: Vec<Box<dyn GameObject>>,
+    pub rooms: HashMap<String, Vec<Box<dyn GameObject>>>,
+    pub cur_room: String,
+    room_settings: HashMap<String, RoomSettings>,
+
+    /// Each room's viewports, with their cameras where they were left
+    viewports: HashMap<String, Vec<Viewport>>,
+
+    /// Played on room switches into rooms without their own, and the one playing now
+    default_transition: Option<Transition>,
+    transition: Option<ActiveTransition>,
+
+    /// Seconds of game time that have been updated through
+    pub clock: f64,
+
+    /// Where commands from this game's objects go
+    commands: CommandQueue,
+
+    /// How fast game time runs compared to real time, and whether it's stopped
+    time_scale: f32,
+    paused: bool,
+    timers: Timers,
+    tweens: Tweens,
+
+    /// Objects as they were given, for resetting objects that aren't persistent or saved
+    #[cfg(feature = "serde")]
+    init_global_objs: Vec<Box<dyn GameObject>>,
+    init_rooms: HashMap<String, Vec<Box<dyn GameObject>>>
+}
+
+impl Game {
+    /// Rooms without viewports of their own use the default ones, or the whole screen if none
+    pub fn new(
+            global_objs: &[Box<dyn GameObject>],
+            rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            room_settings: &HashMap<String, RoomSettings>,
+            default_viewports: &[Viewport],
+            default_transition: Option<Transition>,
+            start_room: &str) -> Self {
+        let default_viewports = if default_viewports.is_empty() {
+            vec![ Viewport::full_screen() ]
+        } else {
+            default_viewports.to_vec()
+        };
+        let viewports = rooms.keys().map(|name| {
+            let viewports = room_settings.get(name)
+                .map(|settings| settings.viewports.clone())
+                .filter(|viewports| !viewports.is_empty())
+                .unwrap_or_else(|| default_viewports.clone());
+            (name.clone(), viewports)
+        }).collect();
+        Self {
+            global_objs: global_objs.to_vec(),
+            rooms: rooms.clone(),
+            cur_room: start_room.to_string(),
+            room_settings: room_settings.clone(),
+            viewports,
+            default_transition,
+            transition: None,
+            clock: 0.0,
+            commands: CommandQueue::default(),
+            time_scale: 1.0,
+            paused: false,
+            timers: Timers::default(),
+            tweens: Tweens::default(),
+            #[cfg(feature = "serde")]
+            init_global_objs: global_objs.to_vec(),
+            init_rooms: rooms.clone()
+        }
+    }
+
+    /// Pass a key press or release on to every object taking input
+    pub fn key_event(&mut self, code: VirtualKeyCode, state: ElementState) {
+        self.send_input(|obj, globals, rooms, cur_room| match state {
+            ElementState::Pressed => obj.on_key_pressed(code, globals, rooms, cur_room),
+            ElementState::Released => obj.on_key_released(code, globals, rooms, cur_room)
+        });
+    }
+
+    /// Pass a typed character on to every object taking input
+    pub fn char_typed(&mut self, c: char) {
+        self.send_input(|obj, globals, rooms, cur_room| {
+            obj.on_char_typed(c, globals, rooms, cur_room)
+        });
+    }
+
+    /// Pass a mouse event on to every object taking input
+    pub fn mouse_event(&mut self, event: MouseEvent) {
+        self.send_input(|obj, globals, rooms, cur_room| {
+            obj.on_mouse(event, globals, rooms, cur_room)
+        });
+    }
+
+    /// Pass a message on to every object in play, modal or not
+    pub fn message(&mut self, channel: &str, text: &str) {
+        self.send_to_objs(false, |obj, globals, rooms, cur_room| {
+            obj.on_message(channel, text, globals, rooms, cur_room)
+        });
+    }
+
+    /// Call an input callback on globals and then the current room's objects.
+    /// If any of them are modal, only those get it. Nothing does during a blocking transition
+    fn send_input<F>(&mut self, send: F)
+            where F: Fn(
+                &mut Box<dyn GameObject>,
+                &Vec<Box<dyn GameObject>>, &HashMap<String, Vec<Box<dyn GameObject>>>,
+                &mut String
+            ) {
+        if self.transition.as_ref().map(|transition| transition.blocks_input()).unwrap_or(false) {
+            return;
+        }
+        self.send_to_objs(true, send);
+    }
+
+    fn send_to_objs<F>(&mut self, respect_modal: bool, send: F)
+            where F: Fn(
+                &mut Box<dyn GameObject>,
+                &Vec<Box<dyn GameObject>>, &HashMap<String, Vec<Box<dyn GameObject>>>,
+                &mut String
+            ) {
+        self.send_to_indexed_objs(respect_modal, |_, obj, globals, rooms, cur_room| {
+            send(obj, globals, rooms, cur_room)
+        });
+    }
+
+    /// Same as send_to_objs, also giving each object's index among globals and then the room
+    fn send_to_indexed_objs<F>(&mut self, respect_modal: bool, send: F)
+            where F: Fn(
+                usize, &mut Box<dyn GameObject>,
+                &Vec<Box<dyn GameObject>>, &HashMap<String, Vec<Box<dyn GameObject>>>,
+                &mut String
+            ) {
+        let _scope = self.commands.enter();
+        let globals_clone = self.global_objs.clone();
+        let rooms_clone = self.rooms.clone();
+        let room_name = self.cur_room.clone();
+        let mut room = self.rooms[&room_name].clone();
+        let modal = respect_modal
+            && globals_clone.iter().chain(room.iter()).any(|obj| obj.modal());
+        for (index, obj) in self.global_objs.iter_mut().chain(room.iter_mut()).enumerate() {
+            if !modal || obj.modal() {
+                send(index, obj, &globals_clone, &rooms_clone, &mut self.cur_room);
+            }
+        }
+        self.rooms.insert(room_name.clone(), room);
+        if self.cur_room != room_name {
+            self.change_room(&room_name);
+        }
+    }
+
+    /// Finish switching away from a room to the current one. The old room's last frame is what
+    /// the transition starts from, then its objects are reset unless they're persistent
+    fn change_room(&mut self, old_room: &str) {
+        self.start_transition(old_room);
+        let init_room = &self.init_rooms[old_room];
+        if let Some(room) = self.rooms.get_mut(old_room) {
+            for (obj, init_obj) in room.iter_mut().zip(init_room.iter()) {
+                if !obj.persistent() {
+                    *obj = init_obj.clone();
+                }
+            }
+        }
+    }
+
+    /// Play the transition into the current room, if there is one, from how a room looks now
+    fn start_transition(&mut self, old_room: &str) {
+        let transition = self.room_settings.get(&self.cur_room)
+            .and_then(|settings| settings.transition)
+            .or(self.default_transition);
+        self.transition = transition
+            .map(|transition| ActiveTransition::new(transition, self.draw_room(old_room)));
+    }
+
+    /// Take the commands objects have sent since the last time this was called
+    pub(crate) fn drain_commands(&self) -> Vec<Command> {
+        self.commands.drain()
+    }
+
+    /// Start a timer, replacing any with the same name
+    pub fn start_timer(&mut self, timer: Timer) {
+        self.timers.start(timer);
+    }
+
+    pub fn cancel_timer(&mut self, name: &str) {
+        self.timers.cancel(name);
+    }
+
+    /// Play a tween, replacing any with the same name
+    pub fn start_tween(&mut self, name: &str, target: Option<String>, tween: Tween) {
+        self.tweens.start(name, target, tween);
+    }
+
+    pub fn stop_tween(&mut self, name: &str) {
+        self.tweens.stop(name);
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    /// Update every object in play, resetting the room's objects if it's been switched away from.
+    /// Transitions play in real time, but everything else goes by scaled game time
+    pub fn update(&mut self, delta_time: f32) {
+        let _scope = self.commands.enter();
+        if let Some(transition) = &mut self.transition {
+            transition.update(delta_time);
+            if transition.done() {
+                self.transition = None;
+            }
+        }
+        if self.paused {
+            return;
+        }
+        let delta_time = delta_time * self.time_scale;
+        self.clock += delta_time as f64;
+        let globals_clone = self.global_objs.clone();
+        let rooms_clone = self.rooms.clone();
+        let old_cur_room = self.cur_room.clone();
+        let mut room = self.rooms[&old_cur_room].clone();
+        for obj in self.global_objs.iter_mut() {
+            obj.update(delta_time, &globals_clone, &rooms_clone, &mut self.cur_room);
+        }
+        for obj in room.iter_mut() {
+            obj.update(delta_time, &globals_clone, &rooms_clone, &mut self.cur_room);
+        }
+        self.rooms.insert(old_cur_room.clone(), room);
+        if self.cur_room != old_cur_room {
+            self.change_room(&old_cur_room);
+        }
+        self.collide();
+
+        let objs = self.global_objs.iter()
+            .chain(self.rooms[&self.cur_room].iter())
+            .collect::<Vec<_>>();
+        let world_size = self.room_size();
+        if let Some(viewports) = self.viewports.get_mut(&self.cur_room) {
+            for viewport in viewports.iter_mut() {
+                let target = objs.iter()
+                    .filter(|obj| viewport.follows_layer(obj.layer()))
+                    .find_map(|obj| obj.camera_target());
+                if let Some(target) = target {
+                    viewport.camera.follow(target, viewport.size);
+                }
+                viewport.camera.clamp(world_size, viewport.size);
+                viewport.camera.update(delta_time);
+            }
+        }
+
+        for timer in self.timers.update(delta_time) {
+            self.fire_timer(&timer);
+        }
+        let frames = self.tweens.update(delta_time);
+        if !frames.is_empty() {
+            self.play_tweens(&frames);
+        }
+    }
+
+    /// Tell objects in play about everything they overlap and can detect
+    fn collide(&mut self) {
+        let objs = self.global_objs.iter()
+            .chain(self.rooms[&self.cur_room].iter())
+            .collect::<Vec<_>>();
+        let colliders = objs.iter().map(|obj| obj.collider()).collect::<Vec<_>>();
+        let ids = objs.iter().map(|obj| obj.id()).collect::<Vec<_>>();
+        let mut hits = vec![ Vec::new(); objs.len() ];
+        for (a, b) in collision::overlapping_pairs(&colliders) {
+            if let (Some(collider_a), Some(collider_b)) = (&colliders[a], &colliders[b]) {
+                if collider_a.detects(collider_b) {
+                    hits[a].push(ids[b].clone());
+                }
+                if collider_b.detects(collider_a) {
+                    hits[b].push(ids[a].clone());
+                }
+            }
+        }
+        if hits.iter().all(|hits| hits.is_empty()) {
+            return;
+        }
+        self.send_to_indexed_objs(false, |index, obj, globals, rooms, cur_room| {
+            for other_id in hits[index].iter() {
+                obj.on_collision(other_id.as_deref(), globals, rooms, cur_room);
+            }
+        });
+    }
+
+    /// Pass tweens' new values on to their targets, or every object in play without one
+    fn play_tweens(&mut self, frames: &[TweenFrame]) {
+        self.send_to_objs(false, |obj, globals, rooms, cur_room| {
+            let id = obj.id();
+            let aimed_here = |frame: &&TweenFrame| frame.target.is_none() || frame.target == id;
+            for frame in frames.iter().filter(aimed_here) {
+                for (property, value) in frame.values.iter() {
+                    obj.on_tween(&frame.name, property, *value, globals, rooms, cur_room);
+                }
+                if frame.finished {
+                    obj.on_tween_finished(&frame.name, globals, rooms, cur_room);
+                }
+            }
+        });
+    }
+
+    /// Pass a timer going off on to its target, or every object in play without one
+    fn fire_timer(&mut self, timer: &Timer) {
+        self.send_to_objs(false, |obj, globals, rooms, cur_room| {
+            if timer.target.is_some() && obj.id() != timer.target {
+                return;
+            }
+            match &timer.action {
+                TimerAction::Event => obj.on_timer(&timer.name, globals, rooms, cur_room),
+                TimerAction::Message(channel, text) =>
+                    obj.on_message(channel, text, globals, rooms, cur_room)
+            }
+        });
+    }
+
+    /// World size of the current room. Rooms are the size of the screen unless set otherwise
+    pub fn room_size(&self) -> (usize, usize) {
+        self.room_settings.get(&self.cur_room)
+            .and_then(|settings| settings.size)
+            .unwrap_or((SCREEN_WIDTH, SCREEN_HEIGHT))
+    }
+
+    /// Point a viewport's camera at a world position, kept inside the current room
+    pub fn move_camera(&mut self, viewport: usize, pos: (i32, i32)) {
+        let world_size = self.room_size();
+        match self.viewports.get_mut(&self.cur_room).and_then(|vps| vps.get_mut(viewport)) {
+            Some(viewport) => {
+                viewport.camera.pos = pos;
+                viewport.camera.clamp(world_size, viewport.size);
+            }, None => eprintln!("No viewport {} in room '{}'.", viewport, self.cur_room)
+        }
+    }
+
+    /// Shake every viewport in the current room
+    pub fn shake_cameras(&mut self, magnitude: f32, duration: f32) {
+        if let Some(viewports) = self.viewports.get_mut(&self.cur_room) {
+            for viewport in viewports.iter_mut() {
+                viewport.camera.shake(magnitude, duration);
+            }
+        }
+    }
+
+    /// Draw the current frame onto a canvas, blended with the last room's if a transition is
+    /// playing. Fading needs the theme to know what colors are being faded
+    pub fn draw(&self, theme: &Theme) -> Canvas {
+        let canvas = self.draw_room(&self.cur_room);
+        match &self.transition {
+            Some(transition) => transition.composite(&canvas, theme),
+            None => canvas
+        }
+    }
+
+    /// Draw a room onto a canvas, one viewport at a time. In each, the world
+    /// (background and world space objects) is drawn and cropped to the camera first, then
+    /// screen space objects and the foreground go over it, all clipped to the viewport.
+    /// Objects are drawn in layer order
+    fn draw_room(&self, room: &str) -> Canvas {
+        let mut objs = self.global_objs.iter()
+            .chain(self.rooms[room].iter())
+            .collect::<Vec<_>>();
+        // Stable, so objects in the same layer keep their order
+        objs.sort_by_key(|obj| obj.layer());
+
+        let settings = self.room_settings.get(room).cloned().unwrap_or_default();
+        let (width, height) = settings.size.unwrap_or((SCREEN_WIDTH, SCREEN_HEIGHT));
+        let mut canvas = Canvas::new();
+        // Viewports showing the same things share one drawing of the world, cropped differently
+        let mut worlds: Vec<(_, WorldBuffer)> = Vec::new();
+        for viewport in self.viewports.get(room).into_iter().flatten() {
+            let objs = objs.iter().filter(|obj| viewport.shows_layer(obj.layer()));
+            let foreground = settings.foreground.as_ref().filter(|_| viewport.room_art);
+            let (left, top) = (viewport.pos.0 as i32, viewport.pos.1 as i32);
+            let (view_x, view_y) = viewport.camera.view_pos();
+
+            let shown = (viewport.layers.as_ref(), viewport.room_art);
+            let world_index = match worlds.iter().position(|(other, _)| *other == shown) {
+                Some(i) => i,
+                None => {
+                    let mut world = WorldBuffer::new(width, height);
+                    if let Some(background) = settings.background.as_ref().filter(|_| shown.1) {
+                        background.draw_to_world(&mut world);
+                    }
+                    for obj in objs.clone() {
+                        obj.draw_world(&mut world);
+                    }
+                    worlds.push((shown, world));
+                    worlds.len() - 1
+                }
+            };
+            let world = &worlds[world_index].1;
+
+            canvas.set_clip(viewport.pos, viewport.size);
+            for y in 0..viewport.size.1 as i32 {
+                for x in 0..viewport.size.0 as i32 {
+                    let c = world.get(view_x + x, view_y + y).unwrap_or(' ');
+                    let (fg, bg) = world.get_colors(view_x + x, view_y + y).unwrap_or_default();
+                    canvas.set_colored(left + x, top + y, c, fg, bg);
+                }
+            }
+            for obj in objs {
+                obj.draw(&mut canvas);
+            }
+            if let Some(foreground) = foreground {
+                foreground.draw_offset(&mut canvas, (left - view_x, top - view_y));
+            }
+            canvas.reset_clip();
+        }
+        canvas
+    }
+
+    /// Save the state of every object that can be saved
+    #[cfg(feature = "serde")]
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            global_objs: self.global_objs.iter().map(|obj| obj.save()).collect(),
+            rooms: self.rooms.iter()
+                .map(|(name, room)| (name.clone(), room.iter().map(|obj| obj.save()).collect()))
+                .collect(),
+            cur_room: self.cur_room.clone(),
+            clock: self.clock
+        }
+    }
+
+    /// Bring the game back to a saved state. Nothing changes if the snapshot doesn't fit the game
+    #[cfg(feature = "serde")]
+    pub fn restore(
+            &mut self, snapshot: &Snapshot,
+            registry: &Registry) -> Result<(), Box<dyn std::error::Error>> {
+        let restore_objs = |saved_objs: &Vec<Option<_>>, init_objs: &Vec<Box<dyn GameObject>>| {
+            if saved_objs.len() != init_objs.len() {
+                Err("Save file doesn't match the game's objects.")?;
+            }
+            saved_objs.iter().zip(init_objs.iter()).map(|(saved, init_obj)| match saved {
+                Some(saved) => registry.load(saved),
+                None => Ok(init_obj.clone())
+            }).collect::<Result<Vec<Box<dyn GameObject>>, Box<dyn std::error::Error>>>()
+        };
+
+        let global_objs = restore_objs(&snapshot.global_objs, &self.init_global_objs)?;
+        let mut rooms = HashMap::new();
+        for (name, init_room) in self.init_rooms.iter() {
+            let saved_room = snapshot.rooms.get(name)
+                .ok_or(format!("Save file is missing room '{}'.", name))?;
+            rooms.insert(name.clone(), restore_objs(saved_room, init_room)?);
+        }
+        if !rooms.contains_key(&snapshot.cur_room) {
+            Err(format!("Save file is in unknown room '{}'.", snapshot.cur_room))?;
+        }
+
+        self.global_objs = global_objs;
+        self.rooms = rooms;
+        self.cur_room = snapshot.cur_room.clone();
+        self.clock = snapshot.clock;
+        self.transition = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Run a game without a window, stepping it manually at a fixed frame rate
+
+use winit::event::{
+    VirtualKeyCode, ElementState
+};
+use crate::{
+    cmd::Command,
+    game::Game,
+    obj::MouseEvent,
+    diff::{
+        FrameDiff, CellRun
+    },
+    cast::CastRecorder,
+    video::VideoRecorder,
+    env::FRAME_RATE,
+    render::{
+        self, Renderer, OffscreenTarget, WINDOW_SIZE
+    }, screenshot,
+    theme::Theme
+};
+#[cfg(feature = "serde")]
+use crate::save::{
+    Snapshot, Registry
+};
+
+/// Game environment for tests, servers and recordings. Create it w/ EnvironmentBuilder
+pub struct HeadlessEnvironment {
+    game: Game,
+    win_title: String,
+    theme: Theme,
+    /// Seconds stepped through, which unlike the game's clock doesn't change on loading a save
+    run_time: f64,
+    /// With the run time it started at, so casts started partway through begin at 0
+    cast_recorder: Option<(CastRecorder, f64)>,
+    diff: FrameDiff,
+    changes: Vec<CellRun>,
+
+    /// Only there if headless rendering was asked for. Video needs it
+    renderer: Option<(Renderer, OffscreenTarget)>,
+    /// Compares frames with what's in the render target, which only needs drawing when it's stale
+    target_diff: FrameDiff,
+    video_recorder: Option<(VideoRecorder, f64)>,
+
+    #[cfg(feature = "serde")]
+    pub(crate) registry: Registry
+}
+
+impl HeadlessEnvironment {
+    pub(crate) fn new(
+            game: Game, win_title: &str, theme: &Theme,
+            cast_path: Option<&str>, video_path: Option<&str>,
+            renderer: Option<(Renderer, OffscreenTarget)>
+            ) -> Result<Self, Box<dyn std::error::Error>> {
+        let cast_recorder = match cast_path {
+            Some(path) => Some((CastRecorder::create(path, win_title, theme)?, 0.0)),
+            None => None
+        };
+        let mut env = Self {
+            game,
+            win_title: win_title.to_string(),
+            theme: theme.clone(),
+            run_time: 0.0,
+            cast_recorder,
+            diff: FrameDiff::new(),
+            changes: Vec::new(),
+            renderer,
+            target_diff: FrameDiff::new(),
+            video_recorder: None,
+            #[cfg(feature = "serde")]
+            registry: Registry::new()
+        };
+        if let Some(path) = video_path {
+            env.start_video(path)?;
+        }
+        Ok(env)
+    }
+
+    /// Seconds of game time, as restored by loading saves
+    pub fn clock(&self) -> f64 {
+        self.game.clock
+    }
+
+    pub fn cur_room(&self) -> &str {
+        &self.game.cur_room
+    }
+
+    pub fn key_pressed(&mut self, code: VirtualKeyCode) {
+        self.game.key_event(code, ElementState::Pressed);
+    }
+
+    pub fn key_released(&mut self, code: VirtualKeyCode) {
+        self.game.key_event(code, ElementState::Released);
+    }
+
+    pub fn char_typed(&mut self, c: char) {
+        self.game.char_typed(c);
+    }
+
+    pub fn mouse_event(&mut self, event: MouseEvent) {
+        self.game.mouse_event(event);
+    }
+
+    /// Cells that changed in the last step, for passing frames on to terminals or over a network
+    pub fn changes(&self) -> &[CellRun] {
+        &self.changes
+    }
+
+    /// Make the next step report every cell as changed, like when a new client needs a full frame
+    pub fn redraw_all(&mut self) {
+        self.diff.reset();
+    }
+
+    /// Update the game by one frame and draw it, returning the text buffer
+    pub fn step(&mut self) -> Result<[[char; 81]; 25], Box<dyn std::error::Error>> {
+        let delta_time = 1.0 / FRAME_RATE;
+        self.game.update(delta_time as f32);
+        self.run_time += delta_time;
+
+        let canvas = self.game.draw(&self.theme);
+        self.changes = self.diff.update(&canvas);
+        let mut screenshot_path = None;
+        for command in self.game.drain_commands() {
+            match command {
+                Command::Screenshot(path) => screenshot_path = Some(path),
+                Command::StartCast(path) => {
+                    self.stop_cast()?;
+                    self.cast_recorder = Some((
+                        CastRecorder::create(&path, &self.win_title, &self.theme)?,
+                        self.run_time
+                    ));
+                }, Command::StopCast => self.stop_cast()?,
+                Command::StartVideo(path) => self.start_video(&path)?,
+                Command::StopVideo => self.stop_video()?,
+                Command::MoveCamera(viewport, x, y) => self.game.move_camera(viewport, (x, y)),
+                Command::ShakeCamera(magnitude, duration) =>
+                    self.game.shake_cameras(magnitude, duration),
+                Command::SetTheme(theme) => self.set_theme(theme),
+                Command::SetPalette(palette) => self.set_theme(self.theme.set_palette(&palette)),
+                Command::SetPaletteColor(index, color) =>
+                    self.set_theme(self.theme.set_palette_color(index, color)),
+                Command::SetEffectParams(name, params) => {
+                    if let Some((renderer, _)) = &mut self.renderer {
+                        renderer.set_effect_params(&name, params)?;
+                    }
+                    self.target_diff.reset();
+                }, Command::SetEffectEnabled(name, enabled) => {
+                    if let Some((renderer, _)) = &mut self.renderer {
+                        renderer.set_effect_enabled(&name, enabled)?;
+                    }
+                    self.target_diff.reset();
+                }, Command::StartTimer(timer) => self.game.start_timer(timer),
+                Command::CancelTimer(name) => self.game.cancel_timer(&name),
+                Command::StartTween(name, target, tween) =>
+                    self.game.start_tween(&name, target, tween),
+                Command::StopTween(name) => self.game.stop_tween(&name),
+                Command::SetTimeScale(time_scale) => self.game.set_time_scale(time_scale),
+                Command::SetPaused(paused) => self.game.set_paused(paused),
+                Command::Message(channel, text) => self.game.message(&channel, &text),
+                #[cfg(feature = "serde")]
+                Command::Save(path) => self.save(&path)?,
+                #[cfg(feature = "serde")]
+                Command::Load(path) => self.load(&path)?
+            }
+        }
+
+        if let Some((recorder, start)) = &mut self.cast_recorder {
+            recorder.record(self.run_time - *start, &canvas)?;
+        }
+        let wants_frame = self.video_recorder.as_ref()
+            .map(|(video, start)| video.wants_frame(self.run_time - start))
+            .unwrap_or(false);
+        if let Some((renderer, target)) = &mut self.renderer {
+            // Only compare frames that get drawn, so the diff matches what's in the target
+            let wanted = wants_frame || screenshot_path.is_some();
+            if wanted && (self.target_diff.changed(&canvas) || renderer.has_effects()) {
+                renderer.render(&canvas, &target.view)?;
+            }
+            if let Some((video, start)) = &mut self.video_recorder {
+                if wants_frame {
+                    let pixels = render::read_rgba(
+                        &renderer.device, &renderer.queue, &target.texture
+                    )?;
+                    video.record(self.run_time - *start, pixels)?;
+                }
+            }
+            if let Some(path) = &screenshot_path {
+                screenshot::save_png(&renderer.device, &renderer.queue, &target.texture, path)?;
+            }
+        }
+        if let Some(path) = &screenshot_path {
+            // Without headless rendering, there's still the text to save
+            screenshot::save_text(&canvas, &self.theme, path)?;
+        }
+        Ok(*canvas.buffer())
+    }
+
+    /// Step through a number of frames
+    pub fn run(&mut self, frames: usize) -> Result<(), Box<dyn std::error::Error>> {
+        for _ in 0..frames {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    /// Finish the asciinema recording in progress, if any
+    pub fn stop_cast(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some((recorder, start)) = self.cast_recorder.take() {
+            recorder.finish(self.run_time - start)?;
+        }
+        Ok(())
+    }
+
+    /// Start recording video from the current frame on. Needs headless rendering turned on
+    pub fn start_video(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if self.renderer.is_none() {
+            Err("Recording video without a window needs headless rendering enabled.")?;
+        }
+        self.stop_video()?;
+        self.video_recorder = Some((
+            VideoRecorder::create(path, WINDOW_SIZE.width, WINDOW_SIZE.height)?,
+            self.run_time
+        ));
+        Ok(())
+    }
+
+    /// Finish the video recording in progress, if any
+    pub fn stop_video(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some((recorder, start)) = self.video_recorder.take() {
+            recorder.finish(self.run_time - start)?;
+        }
+        Ok(())
+    }
+
+    /// Recolor everything from the next frame on, including any recording in progress
+    pub fn set_theme(&mut self, theme: Theme) {
+        if let Some((renderer, _)) = &mut self.renderer {
+            renderer.set_theme(&theme);
+        }
+        if let Some((recorder, _)) = &mut self.cast_recorder {
+            recorder.set_theme(&theme);
+        }
+        self.theme = theme;
+        self.target_diff.reset();
+    }
+
+    /// Write a snapshot of the game to a save file. Format is picked by extension
+    #[cfg(feature = "serde")]
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.game.snapshot().save(path)
+    }
+
+    /// Restore the game from a save file, using the builder's registry to recreate objects
+    #[cfg(feature = "serde")]
+    pub fn load(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.game.restore(&Snapshot::load(path)?, &self.registry)
+    }
+}
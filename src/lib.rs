@@ -1,6 +1,34 @@
 // Author(s): Dylan Turner <dylan.turner@tutanota.com>
 //! Entry point for pseudo-term library
 
+pub mod ansi;
+pub mod art;
+pub mod camera;
+pub mod canvas;
+pub mod cast;
+mod cells;
+pub mod cmd;
+pub mod collision;
+pub mod color;
+pub mod cp437;
+pub mod diff;
 pub mod env;
+mod font;
+mod game;
+pub mod headless;
 pub mod obj;
-
+pub mod post;
+mod render;
+#[cfg(feature = "serde")]
+pub mod room;
+#[cfg(feature = "serde")]
+pub mod save;
+pub mod screenshot;
+pub mod text;
+pub mod theme;
+pub mod tileset;
+pub mod timer;
+pub mod transition;
+pub mod tween;
+pub mod ui;
+pub mod video;
@@ -2,9 +2,40 @@
 //! Trait that can be used to define game objects and what they look like/do
 
 use std::collections::HashMap;
-use winit::event::VirtualKeyCode;
+use winit::event::{
+    VirtualKeyCode, MouseButton
+};
+use crate::{
+    camera::WorldBuffer,
+    canvas::Canvas,
+    collision::Collider,
+    tween::TweenValue
+};
+#[cfg(feature = "serde")]
+use crate::save::SavedObj;
+
+/// What the mouse did, with where it was in screen cells
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MouseEvent {
+    Moved((i32, i32)),
+    Pressed(MouseButton, (i32, i32)),
+    Released(MouseButton, (i32, i32)),
+    /// Lines scrolled, positive for up
+    Scrolled(i32, (i32, i32))
+}
+
+impl MouseEvent {
+    pub fn pos(&self) -> (i32, i32) {
+        match self {
+            Self::Moved(pos) | Self::Pressed(_, pos)
+                | Self::Released(_, pos) | Self::Scrolled(_, pos) => *pos
+        }
+    }
+}
 
 /// An object with animations, position, and behaviors. Takes an enum as generic arg
+// Callbacks take &Vec since changing them to slices would break every existing implementation
+#[allow(clippy::ptr_arg)]
 pub trait GameObject: GameObjectClone + Sync + Send {
     /// User defined type for classifying objects for help in implementing behaviors
     fn obj_type(&self) -> String where Self: Sized + Clone;
@@ -12,6 +43,11 @@ pub trait GameObject: GameObjectClone + Sync + Send {
     /// Whether or not an object resets everytime its room is switched to or not
     fn persistent(&self) -> bool;
 
+    /// Name for singling the object out, like as a timer's target. Doesn't have to be unique
+    fn id(&self) -> Option<String> {
+        None
+    }
+
     /// Tell the GameObject what to do when a key is pressed
     fn on_key_pressed(
         &mut self, code: VirtualKeyCode,
@@ -28,6 +64,61 @@ pub trait GameObject: GameObjectClone + Sync + Send {
         cur_room: &mut String
     );
 
+    /// Tell the GameObject a character was typed, for text entry. Comes after the key press
+    fn on_char_typed(
+        &mut self, _c: char,
+        _global_objs: &Vec<Box<dyn GameObject>>,
+        _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+        _cur_room: &mut String) {}
+
+    /// Tell the GameObject the mouse moved, clicked or scrolled over the screen
+    fn on_mouse(
+        &mut self, _event: MouseEvent,
+        _global_objs: &Vec<Box<dyn GameObject>>,
+        _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+        _cur_room: &mut String) {}
+
+    /// Tell the GameObject about a message some object sent on a channel with Command::Message
+    fn on_message(
+        &mut self, _channel: &str, _text: &str,
+        _global_objs: &Vec<Box<dyn GameObject>>,
+        _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+        _cur_room: &mut String) {}
+
+    /// Tell the GameObject a timer aimed at it, or at everything, went off
+    fn on_timer(
+        &mut self, _name: &str,
+        _global_objs: &Vec<Box<dyn GameObject>>,
+        _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+        _cur_room: &mut String) {}
+
+    /// Tell the GameObject a property a tween aimed at it, or at everything, moved to a new value
+    fn on_tween(
+        &mut self, _name: &str, _property: &str, _value: TweenValue,
+        _global_objs: &Vec<Box<dyn GameObject>>,
+        _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+        _cur_room: &mut String) {}
+
+    /// Tell the GameObject a tween aimed at it, or at everything, played to the end
+    fn on_tween_finished(
+        &mut self, _name: &str,
+        _global_objs: &Vec<Box<dyn GameObject>>,
+        _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+        _cur_room: &mut String) {}
+
+    /// Tell the GameObject it's overlapping another object it can detect, by the other's id.
+    /// Comes every update they overlap, after objects have updated
+    fn on_collision(
+        &mut self, _other_id: Option<&str>,
+        _global_objs: &Vec<Box<dyn GameObject>>,
+        _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+        _cur_room: &mut String) {}
+
+    /// While any object in play is modal, like an open dialog box, only modal objects get input
+    fn modal(&self) -> bool {
+        false
+    }
+
     /// How to continuously modify the object
     fn update(
         &mut self, delta_time: f32,
@@ -36,9 +127,35 @@ pub trait GameObject: GameObjectClone + Sync + Send {
         cur_room: &mut String
     );
 
-    /// Allow drawing to the screen buffer each render frame.
-    /// Chars at index 80 are new lines, so change them at your own risk!
-    fn draw(&self, text_buf: &mut [[char; 81]; 25]);
+    /// Allow drawing to the screen each render frame, in screen coordinates
+    fn draw(&self, canvas: &mut Canvas);
+
+    /// Draw in world coordinates, which the camera scrolls over.
+    /// Things that stay put on screen like a HUD should use draw instead
+    fn draw_world(&self, _world: &mut WorldBuffer) {}
+
+    /// World position for the camera to center on. The first object giving one is followed
+    fn camera_target(&self) -> Option<(i32, i32)> {
+        None
+    }
+
+    /// Cells the object takes up for collisions, and which objects it collides with.
+    /// Objects without one don't collide
+    fn collider(&self) -> Option<Collider> {
+        None
+    }
+
+    /// Objects on higher layers are drawn over lower ones. Within a layer, globals come first
+    fn layer(&self) -> i32 {
+        0
+    }
+
+    /// State to put in save files, usually SavedObj::new(type name, self).ok().
+    /// Objects that return None come back as they started when a save is loaded
+    #[cfg(feature = "serde")]
+    fn save(&self) -> Option<SavedObj> {
+        None
+    }
 }
 
 /// Allows us to store GameObjects in Vecs
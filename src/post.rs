@@ -0,0 +1,326 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Post-processing effects run over each finished frame, like CRT curvature and scanlines.
+//! Effects are WGSL, so games can bring their own
+
+use std::{
+    borrow::Cow,
+    fs,
+    time::Instant
+};
+use wgpu::{
+    Device, Queue, TextureFormat, Texture, TextureView, TextureViewDescriptor, TextureDescriptor,
+    TextureDimension, TextureUsages, Extent3d, Buffer, BufferDescriptor, BufferUsages, BindGroup,
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, BindingResource, BufferBindingType, ShaderStages,
+    TextureSampleType, TextureViewDimension, SamplerDescriptor, SamplerBindingType,
+    FilterMode, AddressMode, ShaderSource, ShaderModuleDescriptor, PipelineLayoutDescriptor,
+    RenderPipelineDescriptor, RenderPipeline, CommandEncoder, RenderPassDescriptor,
+    RenderPassColorAttachment, Operations, LoadOp, Color, ErrorFilter
+};
+use crate::render::WINDOW_SIZE;
+
+/// Comes before every effect's source
+const PRELUDE: &str = include_str!("post_shader.wgsl");
+/// Params, then resolution, time and padding
+const POST_SIZE: usize = 4 * (4 + 2 + 1 + 1);
+
+/// One pass over the frame. Its WGSL defines `fn effect(uv: vec2<f32>) -> vec4<f32>`, which
+/// can read the frame so far with `sample_frame(uv)` and gets `post.params`, `post.resolution`
+/// in pixels and `post.time` in seconds
+#[derive(Clone, Debug, PartialEq)]
+pub struct Effect {
+    pub(crate) name: String,
+    pub(crate) source: String,
+    pub(crate) params: [f32; 4],
+    pub(crate) enabled: bool
+}
+
+impl Effect {
+    /// An effect from WGSL source, with a name for changing it at runtime
+    pub fn custom(name: &str, source: &str, params: [f32; 4]) -> Self {
+        Self {
+            name: name.to_string(),
+            source: source.to_string(),
+            params,
+            enabled: true
+        }
+    }
+
+    /// Same as custom, reading the source from a file
+    pub fn load(
+            name: &str, path: &str,
+            params: [f32; 4]) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::custom(name, &fs::read_to_string(path)?, params))
+    }
+
+    /// Bend the frame like a CRT's glass, by about 0.1 to 0.3. Named "crt"
+    pub fn crt(curvature: f32) -> Self {
+        Self::custom("crt", include_str!("effects/crt.wgsl"), [ curvature, 0.0, 0.0, 0.0 ])
+    }
+
+    /// Darken lines across the frame, from 0 to 1, with a number of lines down the screen.
+    /// Named "scanlines"
+    pub fn scanlines(intensity: f32, count: f32) -> Self {
+        Self::custom(
+            "scanlines", include_str!("effects/scanlines.wgsl"), [ intensity, count, 0.0, 0.0 ]
+        )
+    }
+
+    /// Bleed light around bright text, reaching a radius in pixels. Named "glow"
+    pub fn glow(strength: f32, radius: f32) -> Self {
+        Self::custom("glow", include_str!("effects/glow.wgsl"), [ strength, radius, 0.0, 0.0 ])
+    }
+
+    /// Split red and blue apart toward the edges, by some pixels at the corners.
+    /// Named "chromatic_aberration"
+    pub fn chromatic_aberration(offset: f32) -> Self {
+        Self::custom(
+            "chromatic_aberration", include_str!("effects/chromatic_aberration.wgsl"),
+            [ offset, 0.0, 0.0, 0.0 ]
+        )
+    }
+
+    /// Darken the corners, from 0 to 1, starting some distance from the center with 0.7 being
+    /// about the corners. Named "vignette"
+    pub fn vignette(strength: f32, radius: f32) -> Self {
+        Self::custom(
+            "vignette", include_str!("effects/vignette.wgsl"), [ strength, radius, 0.0, 0.0 ]
+        )
+    }
+
+    /// Start with the effect turned off, for turning on later
+    pub fn set_enabled(&self, enabled: bool) -> Self {
+        let mut new = self.clone();
+        new.enabled = enabled;
+        new
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn params(&self) -> [f32; 4] {
+        self.params
+    }
+}
+
+/// An effect ready to draw, with its uniforms and a bind group for reading either target
+struct Pass {
+    effect: Effect,
+    pipeline: RenderPipeline,
+    uniforms: Buffer,
+    bind_groups: [BindGroup; 2]
+}
+
+/// Runs a frame through every enabled effect in order. The frame is drawn into one target,
+/// then effects go back and forth between the two, with the last drawing to the real view
+pub(crate) struct PostChain {
+    passes: Vec<Pass>,
+    targets: [(Texture, TextureView); 2],
+    start: Instant
+}
+
+impl PostChain {
+    /// Compile every effect, failing if any of them don't
+    pub async fn new(
+            device: &Device, format: TextureFormat,
+            effects: &[Effect]) -> Result<Self, Box<dyn std::error::Error>> {
+        let targets = [ target(device, format), target(device, format) ];
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false
+                    }, count: None
+                }, BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None
+                }, BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                    }, count: None
+                }
+            ]
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[ &bind_group_layout ],
+            push_constant_ranges: &[]
+        });
+
+        let mut passes = Vec::new();
+        for effect in effects {
+            // Games' own shaders can be wrong, which shouldn't bring everything down
+            device.push_error_scope(ErrorFilter::Validation);
+            let shader = device.create_shader_module(ShaderModuleDescriptor {
+                label: Some(&effect.name),
+                source: ShaderSource::Wgsl(Cow::Owned(format!("{}\n{}", PRELUDE, effect.source)))
+            });
+            let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(&effect.name),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[]
+                }, fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(format.into())]
+                }), primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None
+            });
+            if let Some(err) = device.pop_error_scope().await {
+                Err(format!("Effect '{}' failed to compile: {}", effect.name, err))?;
+            }
+
+            let uniforms = device.create_buffer(&BufferDescriptor {
+                label: None,
+                size: POST_SIZE as u64,
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                mapped_at_creation: false
+            });
+            let bind_group = |(_, view): &(Texture, TextureView)| {
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: None,
+                    layout: &bind_group_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(view)
+                        }, BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::Sampler(&sampler)
+                        }, BindGroupEntry {
+                            binding: 2,
+                            resource: uniforms.as_entire_binding()
+                        }
+                    ]
+                })
+            };
+            let bind_groups = [ bind_group(&targets[0]), bind_group(&targets[1]) ];
+            passes.push(Pass {
+                effect: effect.clone(),
+                pipeline,
+                uniforms,
+                bind_groups
+            });
+        }
+
+        Ok(Self {
+            passes,
+            targets,
+            start: Instant::now()
+        })
+    }
+
+    /// Whether any effects are turned on, so frames need to be drawn into the chain first
+    pub fn active(&self) -> bool {
+        self.passes.iter().any(|pass| pass.effect.enabled)
+    }
+
+    /// Where a frame should be drawn for the effects to start from
+    pub fn frame_view(&self) -> &TextureView {
+        &self.targets[0].1
+    }
+
+    /// Change the params of every effect with a name
+    pub fn set_params(
+            &mut self, name: &str, params: [f32; 4]) -> Result<(), Box<dyn std::error::Error>> {
+        self.for_each_named(name, |effect| effect.params = params)
+    }
+
+    /// Turn every effect with a name on or off
+    pub fn set_enabled(
+            &mut self, name: &str, enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+        self.for_each_named(name, |effect| effect.enabled = enabled)
+    }
+
+    fn for_each_named(
+            &mut self, name: &str,
+            change: impl Fn(&mut Effect)) -> Result<(), Box<dyn std::error::Error>> {
+        let mut found = false;
+        for pass in self.passes.iter_mut().filter(|pass| pass.effect.name == name) {
+            change(&mut pass.effect);
+            found = true;
+        }
+        if !found {
+            Err(format!("There's no effect named '{}'.", name))?;
+        }
+        Ok(())
+    }
+
+    /// Run the frame drawn into frame_view through the enabled effects and into a view
+    pub fn apply(&self, queue: &Queue, encoder: &mut CommandEncoder, view: &TextureView) {
+        let time = self.start.elapsed().as_secs_f32();
+        let passes = self.passes.iter().filter(|pass| pass.effect.enabled).collect::<Vec<_>>();
+        for (i, pass) in passes.iter().enumerate() {
+            let mut uniforms = Vec::with_capacity(POST_SIZE);
+            for value in pass.effect.params.iter().chain(&[
+                WINDOW_SIZE.width as f32, WINDOW_SIZE.height as f32, time, 0.0
+            ]) {
+                uniforms.extend_from_slice(&value.to_le_bytes());
+            }
+            queue.write_buffer(&pass.uniforms, 0, &uniforms);
+
+            let dest = if i + 1 == passes.len() {
+                view
+            } else {
+                &self.targets[(i + 1) % 2].1
+            };
+            let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: dest,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: true
+                    }
+                })], depth_stencil_attachment: None
+            });
+            rpass.set_pipeline(&pass.pipeline);
+            rpass.set_bind_group(0, &pass.bind_groups[i % 2], &[]);
+            rpass.draw(0..3, 0..1);
+        }
+    }
+}
+
+/// A window-sized texture effects can draw into and read from
+fn target(device: &Device, format: TextureFormat) -> (Texture, TextureView) {
+    let texture = device.create_texture(&TextureDescriptor {
+        label: None,
+        size: Extent3d {
+            width: WINDOW_SIZE.width,
+            height: WINDOW_SIZE.height,
+            depth_or_array_layers: 1
+        }, mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[]
+    });
+    let view = texture.create_view(&TextureViewDescriptor::default());
+    (texture, view)
+}
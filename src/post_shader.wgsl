@@ -0,0 +1,41 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+// Description: Shared by every post-processing effect, which comes after this and defines
+// fn effect(uv: vec2<f32>) -> vec4<f32>, reading the frame so far through sample_frame
+
+struct Post {
+    params: vec4<f32>,
+    resolution: vec2<f32>,
+    time: f32,
+    padding: f32
+};
+
+@group(0) @binding(0)
+var frame: texture_2d<f32>;
+@group(0) @binding(1)
+var frame_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> post: Post;
+
+struct VertexOut {
+    @builtin(position) clip_pos: vec4<f32>,
+    @location(0) uv: vec2<f32>
+};
+
+// One triangle big enough to cover the screen
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOut {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    var out: VertexOut;
+    out.clip_pos = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn sample_frame(uv: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(frame, frame_sampler, uv, 0.0);
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    return effect(in.uv);
+}
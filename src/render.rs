@@ -0,0 +1,415 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Draw the text buffer with wgpu, either to the window's surface or to an offscreen texture
+
+use std::{
+    borrow::Cow,
+    sync::mpsc
+};
+use wgpu::{
+    Instance, RequestAdapterOptions, PowerPreference, Adapter, Device, Queue, Surface, Features,
+    Limits, DeviceDescriptor, TextureFormat, Texture, TextureView, TextureViewDescriptor,
+    TextureDescriptor, TextureDimension, TextureUsages, Extent3d, CommandEncoderDescriptor,
+    RenderPassDescriptor, RenderPassColorAttachment, Operations, LoadOp, Color, ShaderSource,
+    ShaderModuleDescriptor, PipelineLayoutDescriptor, RenderPipelineDescriptor, RenderPipeline,
+    BufferDescriptor, BufferUsages, ImageCopyBuffer, ImageDataLayout, MapMode, Maintain, Buffer,
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, BufferBindingType, ShaderStages,
+    COPY_BYTES_PER_ROW_ALIGNMENT
+};
+use winit::dpi::PhysicalSize;
+use crate::{
+    canvas::{
+        Canvas, SCREEN_WIDTH, SCREEN_HEIGHT
+    }, cells::{
+        CellRenderer, Cell
+    }, font::{
+        FontChain, GlyphCache
+    }, post::{
+        Effect, PostChain
+    }, theme::Theme,
+    tileset::Tileset
+};
+
+/// The terminal will appear to be 80x25, but this is the size of the window surrounding that
+pub(crate) const WINDOW_SIZE: PhysicalSize<u32> = PhysicalSize { width: 1024, height: 576 };
+/// Grid rectangle, bg and border colors, then screen size, border width and padding
+const CHROME_SIZE: usize = 4 * (4 * 3 + 2 + 1 + 1);
+/// Format used when there's no surface to match
+const OFFSCREEN_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+/// Find a gpu and open it. Without a surface to draw to, fall back to a software adapter if needed
+pub(crate) async fn request_device(
+        instance: &Instance,
+        surface: Option<&Surface>) -> Result<(Adapter, Device, Queue), Box<dyn std::error::Error>> {
+    let mut adapter = instance.request_adapter(&RequestAdapterOptions {
+        power_preference: PowerPreference::default(),
+        force_fallback_adapter: false,
+        compatible_surface: surface
+    }).await;
+    if adapter.is_none() && surface.is_none() {
+        adapter = instance.request_adapter(&RequestAdapterOptions {
+            power_preference: PowerPreference::default(),
+            force_fallback_adapter: true,
+            compatible_surface: None
+        }).await;
+    }
+    if adapter.is_none() {
+        Err("Failed to find an appropriate adapter.")?;
+    }
+    let adapter = adapter.unwrap();
+    let (device, queue) = adapter.request_device(
+        &DeviceDescriptor {
+            label: None,
+            features: Features::empty(),
+            limits: Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits())
+        }, None
+    ).await?;
+    Ok((adapter, device, queue))
+}
+
+/// Where the tiles the grid is drawn with come from
+enum Glyphs {
+    Font(GlyphCache),
+    Tiles
+}
+
+/// Draws the terminal background and text into a texture of a given format
+pub(crate) struct Renderer {
+    pub device: Device,
+    pub queue: Queue,
+    pub format: TextureFormat,
+    render_pipeline: RenderPipeline,
+    chrome: Buffer,
+    chrome_bind_group: BindGroup,
+    glyphs: Glyphs,
+    cells: CellRenderer,
+    post: Option<PostChain>,
+    theme: Theme
+}
+
+impl Renderer {
+    /// Draw with a tileset if there is one, otherwise the fonts
+    pub fn new(
+            device: Device, queue: Queue, format: TextureFormat,
+            fonts: &FontChain,
+            tileset: Option<&Tileset>) -> Result<Self, Box<dyn std::error::Error>> {
+        let bg_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: None,
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("bg_shader.wgsl"))),
+        });
+        let chrome = device.create_buffer(&BufferDescriptor {
+            label: None,
+            size: CHROME_SIZE as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None
+                    }, count: None
+                }
+            ]
+        });
+        let chrome_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: chrome.as_entire_binding()
+                }
+            ]
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[ &bind_group_layout ],
+            push_constant_ranges: &[],
+        });
+        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &bg_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            }, fragment: Some(wgpu::FragmentState {
+                module: &bg_shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+            }), primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None
+        });
+
+        // Fonts are drawn into their atlas as glyphs come up
+        let (glyphs, cells) = match tileset {
+            Some(tileset) => {
+                let mut cells = CellRenderer::new(&device, format, tileset.tile_size());
+                cells.set_atlas(
+                    &device, &queue, &tileset.pixels, (tileset.width, tileset.height)
+                );
+                (Glyphs::Tiles, cells)
+            }, None => {
+                let cache = GlyphCache::new(fonts, device.limits().max_texture_dimension_2d);
+                let cells = CellRenderer::new(&device, format, cache.tile_size());
+                (Glyphs::Font(cache), cells)
+            }
+        };
+
+        let mut renderer = Self {
+            device,
+            queue,
+            format,
+            render_pipeline,
+            chrome,
+            chrome_bind_group,
+            glyphs,
+            cells,
+            post: None,
+            theme: Theme::default()
+        };
+        renderer.set_theme(&Theme::default());
+        Ok(renderer)
+    }
+
+    /// Color everything from here on with a different theme
+    pub fn set_theme(&mut self, theme: &Theme) {
+        let (left, top) = self.cells.origin();
+        let (cell_width, cell_height) = self.cells.cell_size();
+        let (columns, rows) = self.cells.grid_size();
+        let mut chrome = Vec::with_capacity(CHROME_SIZE);
+        for value in [
+            left, top,
+            left + cell_width * columns as f32, top + cell_height * rows as f32
+        ].iter().chain(&theme.bg).chain(&theme.border).chain(&[
+            WINDOW_SIZE.width as f32, WINDOW_SIZE.height as f32, theme.border_width, 0.0
+        ]) {
+            chrome.extend_from_slice(&value.to_le_bytes());
+        }
+        self.queue.write_buffer(&self.chrome, 0, &chrome);
+        self.theme = theme.clone();
+    }
+
+    /// Run frames through post-processing effects in order, replacing any there were
+    pub async fn set_effects(
+            &mut self, effects: &[Effect]) -> Result<(), Box<dyn std::error::Error>> {
+        self.post = if effects.is_empty() {
+            None
+        } else {
+            Some(PostChain::new(&self.device, self.format, effects).await?)
+        };
+        Ok(())
+    }
+
+    /// Whether frames go through effects, which can look different even when the grid doesn't
+    pub fn has_effects(&self) -> bool {
+        self.post.as_ref().map(|post| post.active()).unwrap_or(false)
+    }
+
+    /// Change the params of the effects with a name
+    pub fn set_effect_params(
+            &mut self, name: &str, params: [f32; 4]) -> Result<(), Box<dyn std::error::Error>> {
+        match &mut self.post {
+            Some(post) => post.set_params(name, params),
+            None => Err(format!("There's no effect named '{}'.", name))?
+        }
+    }
+
+    /// Turn the effects with a name on or off
+    pub fn set_effect_enabled(
+            &mut self, name: &str, enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+        match &mut self.post {
+            Some(post) => post.set_enabled(name, enabled),
+            None => Err(format!("There's no effect named '{}'.", name))?
+        }
+    }
+
+    /// Draw a frame into a texture view, looking each cell's colors up in the theme
+    pub fn render(
+            &mut self, canvas: &Canvas,
+            view: &TextureView) -> Result<(), Box<dyn std::error::Error>> {
+        let grid_size = (canvas.width(), canvas.height());
+        if self.cells.set_grid_size(&self.device, &self.queue, grid_size) {
+            // The grid's rectangle moved
+            self.set_theme(&self.theme.clone());
+        }
+        let mut cells = Vec::with_capacity(grid_size.0 * grid_size.1);
+        let lines = canvas.buffer().iter().zip(canvas.color_buffer());
+        for (c, colors) in lines.flat_map(|(line, colors)| line.iter().zip(colors)) {
+            let tile = match &mut self.glyphs {
+                Glyphs::Font(cache) => cache.tile_for(*c),
+                Glyphs::Tiles => Tileset::tile_for(*c)
+            };
+            let (fg, bg) = self.theme.resolve(*colors);
+            cells.push(Cell {
+                tile,
+                fg,
+                bg
+            });
+        }
+        if let Glyphs::Font(cache) = &mut self.glyphs {
+            if cache.take_changed() {
+                self.cells.set_atlas(&self.device, &self.queue, cache.pixels(), cache.size());
+            }
+        }
+        self.cells.prepare(&self.queue, &cells);
+
+        // With effects on, the frame is drawn for them to work from instead
+        let post = self.post.as_ref().filter(|post| post.active());
+        let mut encoder = self.device.create_command_encoder(
+            &CommandEncoderDescriptor { label: None }
+        );
+        {
+            let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: post.map(|post| post.frame_view()).unwrap_or(view),
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color {
+                            r: self.theme.clear[0] as f64,
+                            g: self.theme.clear[1] as f64,
+                            b: self.theme.clear[2] as f64,
+                            a: self.theme.clear[3] as f64
+                        }), store: true
+                    }
+                })], depth_stencil_attachment: None
+            });
+            rpass.set_pipeline(&self.render_pipeline);
+            rpass.set_bind_group(0, &self.chrome_bind_group, &[]);
+            rpass.draw(0..6, 0..1);
+            self.cells.draw(&mut rpass);
+        }
+        if let Some(post) = post {
+            post.apply(&self.queue, &mut encoder, view);
+        }
+        self.queue.submit(Some(encoder.finish()));
+        Ok(())
+    }
+
+    /// Which cell of the grid a point in the window is over, if any
+    pub fn cell_at(&self, pos: (f64, f64)) -> Option<(i32, i32)> {
+        let origin = self.cells.origin();
+        let (cell_width, cell_height) = self.cells.cell_size();
+        let x = ((pos.0 as f32 - origin.0) / cell_width).floor() as i32;
+        let y = ((pos.1 as f32 - origin.1) / cell_height).floor() as i32;
+        if (0..SCREEN_WIDTH as i32).contains(&x) && (0..SCREEN_HEIGHT as i32).contains(&y) {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+}
+
+/// A window-sized texture to render frames into when they need to be read back
+pub(crate) struct OffscreenTarget {
+    pub texture: Texture,
+    pub view: TextureView
+}
+
+impl OffscreenTarget {
+    pub fn new(device: &Device, format: TextureFormat) -> Self {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: None,
+            size: Extent3d {
+                width: WINDOW_SIZE.width,
+                height: WINDOW_SIZE.height,
+                depth_or_array_layers: 1
+            }, mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[]
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+        Self {
+            texture,
+            view
+        }
+    }
+
+    /// Set up a renderer without a window to draw into a target like this
+    pub async fn new_with_renderer(
+            fonts: &FontChain, tileset: Option<&Tileset>,
+            effects: &[Effect], theme: &Theme
+            ) -> Result<(Renderer, Self), Box<dyn std::error::Error>> {
+        let instance = Instance::default();
+        let (_, device, queue) = request_device(&instance, None).await?;
+        let target = Self::new(&device, OFFSCREEN_FORMAT);
+        let mut renderer = Renderer::new(device, queue, OFFSCREEN_FORMAT, fonts, tileset)?;
+        renderer.set_effects(effects).await?;
+        renderer.set_theme(theme);
+        Ok((renderer, target))
+    }
+}
+
+/// Copy a rendered texture back from the gpu as RGBA pixels.
+/// Fails if the texture wasn't created with COPY_SRC usage, like some window surfaces
+pub(crate) fn read_rgba(
+        device: &Device, queue: &Queue,
+        texture: &Texture) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    if !texture.usage().contains(TextureUsages::COPY_SRC) {
+        Err("Texture can't be copied from to read back frames.")?;
+    }
+    let swap_red_blue = match texture.format() {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => false,
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => true,
+        _ => Err("Unsupported texture format for reading back frames.")?
+    };
+    let (width, height) = (texture.width(), texture.height());
+
+    // Rows copied out of a texture must be aligned
+    let row_size = width * 4;
+    let padded_row_size = row_size.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT)
+        * COPY_BYTES_PER_ROW_ALIGNMENT;
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: None,
+        size: (padded_row_size * height) as u64,
+        usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        mapped_at_creation: false
+    });
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_size),
+                rows_per_image: None
+            }
+        }, Extent3d { width, height, depth_or_array_layers: 1 }
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    let (sender, receiver) = mpsc::channel();
+    slice.map_async(MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(Maintain::Wait);
+    receiver.recv()??;
+    let mut pixels = Vec::with_capacity((row_size * height) as usize);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks(padded_row_size as usize) {
+            pixels.extend_from_slice(&row[..row_size as usize]);
+        }
+    }
+    buffer.unmap();
+    if swap_red_blue {
+        for pixel in pixels.chunks_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    Ok(pixels)
+}
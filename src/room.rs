@@ -0,0 +1,159 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Rooms defined in RON, TOML or JSON files, so levels can change without recompiling
+
+use std::{
+    fs,
+    path::Path
+};
+use serde::Deserialize;
+use crate::{
+    obj::GameObject,
+    art::StaticArt,
+    camera::Viewport,
+    save::Registry
+};
+
+fn empty_params() -> serde_json::Value {
+    serde_json::Value::Object(serde_json::Map::new())
+}
+
+/// An object in a room file: its registered type name and what to construct it with
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ObjDef {
+    #[serde(rename = "type")]
+    pub obj_type: String,
+    #[serde(default = "empty_params")]
+    pub params: serde_json::Value
+}
+
+/// A room as written in a file. The background and foreground are static art drawn behind and
+/// in front of the objects, either written inline or loaded from a text or .ans file next to the
+/// room file. Size is the room's world size, if it's bigger than the screen
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct RoomDef {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub size: Option<(usize, usize)>,
+    #[serde(default)]
+    pub background: Option<String>,
+    #[serde(default)]
+    pub background_file: Option<String>,
+    #[serde(default)]
+    pub foreground: Option<String>,
+    #[serde(default)]
+    pub foreground_file: Option<String>,
+    #[serde(default)]
+    pub viewports: Vec<ViewportDef>,
+    #[serde(default)]
+    pub objects: Vec<ObjDef>
+}
+
+fn default_room_art() -> bool {
+    true
+}
+
+/// A viewport in a room file. Leaving out layers shows all of them
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ViewportDef {
+    pub pos: (usize, usize),
+    pub size: (usize, usize),
+    #[serde(default)]
+    pub layers: Option<Vec<i32>>,
+    #[serde(default)]
+    pub follow_layer: Option<i32>,
+    #[serde(default = "default_room_art")]
+    pub room_art: bool
+}
+
+impl ViewportDef {
+    pub fn to_viewport(&self) -> Viewport {
+        let viewport = Viewport::new(self.pos, self.size)
+            .set_follow_layer(self.follow_layer)
+            .set_room_art(self.room_art);
+        match &self.layers {
+            Some(layers) => viewport.set_layers(layers),
+            None => viewport
+        }
+    }
+}
+
+/// Art written inline or in a file. Both at once isn't allowed
+fn layer(
+        text: &Option<String>, file: &Option<String>,
+        name: &str) -> Result<Option<StaticArt>, Box<dyn std::error::Error>> {
+    Ok(match (text, file) {
+        (Some(_), Some(_)) => Err(format!("Room can't have both {} and {}_file.", name, name))?,
+        (Some(text), None) => Some(StaticArt::new(text, (0, 0))),
+        (None, Some(file)) => Some(StaticArt::load(file, (0, 0))?),
+        (None, None) => None
+    })
+}
+
+/// File formats a room can be written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoomFormat {
+    Ron,
+    Toml,
+    Json
+}
+
+impl RoomFormat {
+    /// Pick a format from a file's extension
+    pub fn from_path(path: &str) -> Option<Self> {
+        let ext = path.rsplit_once('.').map(|(_, ext)| ext.to_lowercase());
+        match ext.as_deref() {
+            Some("ron") => Some(Self::Ron),
+            Some("toml") => Some(Self::Toml),
+            Some("json") => Some(Self::Json),
+            _ => None
+        }
+    }
+}
+
+impl RoomDef {
+    pub fn parse(text: &str, format: RoomFormat) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(match format {
+            RoomFormat::Ron => ron::from_str(text)?,
+            RoomFormat::Toml => toml::from_str(text)?,
+            RoomFormat::Json => serde_json::from_str(text)?
+        })
+    }
+
+    /// Read a room file. Rooms without a name are named after the file,
+    /// and background files are found relative to the room file
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let format = RoomFormat::from_path(path)
+            .ok_or("Room files must be .ron, .toml or .json.")?;
+        let mut room = Self::parse(&fs::read_to_string(path)?, format)?;
+        let path = Path::new(path);
+        if room.name.is_none() {
+            room.name = path.file_stem().map(|stem| stem.to_string_lossy().to_string());
+        }
+        if let Some(dir) = path.parent() {
+            for file in [ &mut room.background_file, &mut room.foreground_file ] {
+                if let Some(layer_file) = file {
+                    *file = Some(dir.join(&layer_file).to_string_lossy().to_string());
+                }
+            }
+        }
+        Ok(room)
+    }
+
+    /// Create the room's objects using the registry's constructors
+    pub fn build(
+            &self,
+            registry: &Registry) -> Result<Vec<Box<dyn GameObject>>, Box<dyn std::error::Error>> {
+        self.objects.iter()
+            .map(|obj| registry.construct(&obj.obj_type, obj.params.clone()))
+            .collect()
+    }
+
+    pub fn background_layer(&self) -> Result<Option<StaticArt>, Box<dyn std::error::Error>> {
+        layer(&self.background, &self.background_file, "background")
+    }
+
+    pub fn foreground_layer(&self) -> Result<Option<StaticArt>, Box<dyn std::error::Error>> {
+        layer(&self.foreground, &self.foreground_file, "foreground")
+    }
+}
@@ -0,0 +1,155 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Snapshots of a running game that can be written to and read from save files
+
+use std::{
+    collections::HashMap,
+    fs,
+    sync::Arc
+};
+use serde::{
+    Serialize, Deserialize,
+    de::DeserializeOwned
+};
+use crate::obj::GameObject;
+
+/// A game object's type name and its state as JSON. Get one with GameObject::save
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedObj {
+    pub obj_type: String,
+    pub data: String
+}
+
+impl SavedObj {
+    /// Serialize an object under the name it was registered with
+    pub fn new<T: Serialize>(obj_type: &str, obj: &T) -> Result<Self, serde_json::Error> {
+        Ok(Self {
+            obj_type: obj_type.to_string(),
+            data: serde_json::to_string(obj)?
+        })
+    }
+}
+
+type Loader = fn(&str) -> Result<Box<dyn GameObject>, Box<dyn std::error::Error>>;
+type Constructor = Arc<
+    dyn Fn(serde_json::Value) -> Result<Box<dyn GameObject>, Box<dyn std::error::Error>>
+        + Send + Sync
+>;
+
+fn load_obj<T>(data: &str) -> Result<Box<dyn GameObject>, Box<dyn std::error::Error>>
+        where T: 'static + GameObject + Clone + DeserializeOwned {
+    Ok(Box::new(serde_json::from_str::<T>(data)?))
+}
+
+/// Knows how to turn saved objects and room file entries back into game objects, by type name
+#[derive(Clone, Default)]
+pub struct Registry {
+    loaders: HashMap<String, Loader>,
+    constructors: HashMap<String, Constructor>
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a type that can be loaded. The name must match the one its save function uses.
+    /// Room files can create it too, with its fields as the params
+    pub fn register<T>(&self, obj_type: &str) -> Self
+            where T: 'static + GameObject + Clone + DeserializeOwned {
+        let mut new = self.clone();
+        new.loaders.insert(obj_type.to_string(), load_obj::<T>);
+        new.constructors.insert(obj_type.to_string(), Arc::new(|params| {
+            Ok(Box::new(serde_json::from_value::<T>(params)?))
+        }));
+        new
+    }
+
+    /// Add a way for room files to create a type from params, replacing any there already.
+    /// This doesn't make it loadable from saves
+    pub fn register_constructor<P>(
+            &self, obj_type: &str, constructor: fn(P) -> Box<dyn GameObject>) -> Self
+            where P: 'static + DeserializeOwned {
+        let mut new = self.clone();
+        new.constructors.insert(obj_type.to_string(), Arc::new(move |params| {
+            Ok(constructor(serde_json::from_value::<P>(params)?))
+        }));
+        new
+    }
+
+    pub fn load(
+            &self, saved: &SavedObj) -> Result<Box<dyn GameObject>, Box<dyn std::error::Error>> {
+        let loader = self.loaders.get(&saved.obj_type)
+            .ok_or(format!("No type registered for saved object '{}'.", saved.obj_type))?;
+        loader(&saved.data)
+    }
+
+    /// Create an object from the params given to it in a room file
+    pub fn construct(
+            &self, obj_type: &str,
+            params: serde_json::Value) -> Result<Box<dyn GameObject>, Box<dyn std::error::Error>> {
+        let constructor = self.constructors.get(obj_type)
+            .ok_or(format!("No type registered for room object '{}'.", obj_type))?;
+        constructor(params)
+    }
+}
+
+/// File formats a snapshot can be written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveFormat {
+    Json,
+    Ron,
+    Bincode
+}
+
+impl SaveFormat {
+    /// Pick a format from a file's extension: .json, .ron, or anything else for bincode
+    pub fn from_path(path: &str) -> Self {
+        let ext = path.rsplit_once('.').map(|(_, ext)| ext.to_lowercase());
+        match ext.as_deref() {
+            Some("json") => Self::Json,
+            Some("ron") => Self::Ron,
+            _ => Self::Bincode
+        }
+    }
+}
+
+/// Everything needed to restore a game: objects, which room is in play, and the engine clock.
+/// Objects that don't save themselves are stored as None and come back as they started
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub global_objs: Vec<Option<SavedObj>>,
+    pub rooms: HashMap<String, Vec<Option<SavedObj>>>,
+    pub cur_room: String,
+    pub clock: f64
+}
+
+impl Snapshot {
+    pub fn to_bytes(&self, format: SaveFormat) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(match format {
+            SaveFormat::Json => serde_json::to_vec_pretty(self)?,
+            SaveFormat::Ron => ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?
+                .into_bytes(),
+            SaveFormat::Bincode => bincode::serialize(self)?
+        })
+    }
+
+    pub fn from_bytes(
+            bytes: &[u8], format: SaveFormat) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(match format {
+            SaveFormat::Json => serde_json::from_slice(bytes)?,
+            SaveFormat::Ron => ron::de::from_bytes(bytes)?,
+            SaveFormat::Bincode => bincode::deserialize(bytes)?
+        })
+    }
+
+    /// Write to a file, picking the format from its extension
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, self.to_bytes(SaveFormat::from_path(path))?)?;
+        Ok(())
+    }
+
+    /// Read from a file, picking the format from its extension
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_bytes(&fs::read(path)?, SaveFormat::from_path(path))
+    }
+}
@@ -0,0 +1,86 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Capture the rendered frame to a PNG and the text buffer to plain text and ANSI
+
+use std::{
+    fs::{
+        self, File
+    }, io::BufWriter,
+    time::{
+        SystemTime, UNIX_EPOCH
+    }
+};
+use wgpu::{
+    Device, Queue, Texture
+};
+use crate::{
+    canvas::Canvas,
+    color,
+    render,
+    theme::Theme
+};
+
+/// A file path (without extension) to save a screenshot to based on the current time
+pub fn default_path() -> String {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or(0);
+    format!("screenshot_{}", millis)
+}
+
+/// The text buffer as plain lines of text
+pub fn buffer_to_text(text_buf: &[[char; 81]; 25]) -> String {
+    text_buf.iter()
+        .map(|line| {
+            line[..80].iter().map(|c| if *c == '\0' { ' ' } else { *c }).collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n") + "\n"
+}
+
+/// A frame as ANSI escaped text in the colors it's drawn with in a theme
+pub fn buffer_to_ansi(frame: &Canvas, theme: &Theme) -> String {
+    let default_colors = theme.resolve(Default::default());
+    let mut ansi = String::new();
+    let mut pen = None;
+    for (line, colors) in buffer_to_text(frame.buffer()).lines().zip(frame.color_buffer()) {
+        for (c, colors) in line.chars().zip(colors) {
+            let colors = theme.resolve(*colors);
+            if pen != Some(colors) {
+                ansi.push_str(&color::escape(colors.0, colors.1));
+                pen = Some(colors);
+            }
+            ansi.push(c);
+        }
+        // The rest of a wider terminal's line gets the theme's background
+        if pen != Some(default_colors) {
+            ansi.push_str(&color::escape(default_colors.0, default_colors.1));
+            pen = Some(default_colors);
+        }
+        ansi.push_str("\x1b[K\n");
+    }
+    ansi.push_str("\x1b[0m");
+    ansi
+}
+
+/// Write a frame to <path>.txt and <path>.ans
+pub fn save_text(
+        frame: &Canvas, theme: &Theme,
+        path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    fs::write(format!("{}.txt", path), buffer_to_text(frame.buffer()))?;
+    fs::write(format!("{}.ans", path), buffer_to_ansi(frame, theme))?;
+    Ok(())
+}
+
+/// Copy a rendered texture back from the gpu and write it to <path>.png.
+/// Fails if the texture wasn't created with COPY_SRC usage, like some window surfaces
+pub fn save_png(
+        device: &Device, queue: &Queue, texture: &Texture,
+        path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let pixels = render::read_rgba(device, queue, texture)?;
+    let file = File::create(format!("{}.png", path))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), texture.width(), texture.height());
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Laying text out in a fixed number of cells: wrapping, alignment and truncation
+
+/// Where a line of text sits in the space it's given
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right
+}
+
+impl Align {
+    /// How many cells in to start a line of some length so it's aligned in a width
+    pub fn offset(&self, len: usize, width: usize) -> usize {
+        match self {
+            Self::Left => 0,
+            Self::Center => width.saturating_sub(len) / 2,
+            Self::Right => width.saturating_sub(len)
+        }
+    }
+}
+
+/// Cut text down to a width, ending it with '…' if anything was cut
+pub fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        text.to_string()
+    } else if width == 0 {
+        String::new()
+    } else {
+        text.chars().take(width - 1).chain(std::iter::once('…')).collect()
+    }
+}
+
+/// Break text into lines no wider than width, between words where possible.
+/// New lines in the text are kept, and words too long for a line are split
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    if width == 0 {
+        return Vec::new();
+    }
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut line_len = 0;
+        for word in paragraph.split_whitespace() {
+            let mut word = word.chars().collect::<Vec<_>>();
+            if line_len > 0 && line_len + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+                line_len = 0;
+            }
+            while word.len() > width {
+                let rest = word.split_off(width);
+                lines.push(word.into_iter().collect());
+                word = rest;
+            }
+            if line_len > 0 {
+                line.push(' ');
+                line_len += 1;
+            }
+            line_len += word.len();
+            line.extend(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_fits_width() {
+        assert_eq!(truncate("hello", 5), "hello");
+        assert_eq!(truncate("hello", 4), "hel…");
+        assert_eq!(truncate("hello", 1), "…");
+        assert_eq!(truncate("hello", 0), "");
+        assert_eq!(truncate("héllo wörld", 6), "héllo…");
+    }
+
+    #[test]
+    fn wrap_between_words() {
+        assert_eq!(wrap("the quick brown fox", 10), vec![ "the quick", "brown fox" ]);
+        assert_eq!(wrap("  spaced   out  ", 20), vec![ "spaced out" ]);
+        assert_eq!(wrap("exactly ten", 7), vec![ "exactly", "ten" ]);
+    }
+
+    #[test]
+    fn wrap_keeps_new_lines() {
+        assert_eq!(wrap("one\n\ntwo", 10), vec![ "one", "", "two" ]);
+        assert_eq!(wrap("", 10), vec![ "" ]);
+    }
+
+    #[test]
+    fn wrap_splits_long_words() {
+        assert_eq!(wrap("a abcdefghij b", 4), vec![ "a", "abcd", "efgh", "ij b" ]);
+        assert_eq!(wrap("ééééé", 2), vec![ "éé", "éé", "é" ]);
+        assert!(wrap("anything", 0).is_empty());
+    }
+}
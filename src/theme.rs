@@ -0,0 +1,155 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Colors of the terminal and what's around it, from its palette to the window behind it
+
+use crate::color::Color;
+
+/// The 16 colors of a VGA text mode, which the palette starts with
+const VGA_COLORS: [[u8; 3]; 16] = [
+    [ 0x00, 0x00, 0x00 ], [ 0xaa, 0x00, 0x00 ], [ 0x00, 0xaa, 0x00 ], [ 0xaa, 0x55, 0x00 ],
+    [ 0x00, 0x00, 0xaa ], [ 0xaa, 0x00, 0xaa ], [ 0x00, 0xaa, 0xaa ], [ 0xaa, 0xaa, 0xaa ],
+    [ 0x55, 0x55, 0x55 ], [ 0xff, 0x55, 0x55 ], [ 0x55, 0xff, 0x55 ], [ 0xff, 0xff, 0x55 ],
+    [ 0x55, 0x55, 0xff ], [ 0xff, 0x55, 0xff ], [ 0x55, 0xff, 0xff ], [ 0xff, 0xff, 0xff ]
+];
+/// Levels of each channel in xterm's 6x6x6 color cube
+const CUBE_LEVELS: [u8; 6] = [ 0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff ];
+
+/// Everything about how the terminal is colored. The palette has 256 colors, the 16 ANSI ones
+/// and then xterm's color cube and grays. Text is drawn in the fg color on the bg color,
+/// with a border of some pixels around the grid and the clear color filling the rest of the window
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub(crate) palette: Vec<[f32; 4]>,
+    pub(crate) fg: [f32; 4],
+    pub(crate) bg: [f32; 4],
+    pub(crate) border: [f32; 4],
+    pub(crate) border_width: f32,
+    pub(crate) clear: [f32; 4]
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+impl Theme {
+    /// Light text on a blue terminal in a black window
+    pub fn classic() -> Self {
+        let mut palette = VGA_COLORS.to_vec();
+        for i in 0..(6 * 6 * 6) {
+            palette.push([
+                CUBE_LEVELS[i / 36], CUBE_LEVELS[(i / 6) % 6], CUBE_LEVELS[i % 6]
+            ]);
+        }
+        for i in 0..24 {
+            let gray = 8 + 10 * i as u8;
+            palette.push([ gray, gray, gray ]);
+        }
+        Self {
+            palette: palette.iter().map(|rgb| rgba(*rgb)).collect(),
+            fg: [ 0.8, 0.85, 0.9, 1.0 ],
+            bg: [ 0.0, 0.0, 0.6, 1.0 ],
+            border: [ 0.0, 0.0, 0.6, 1.0 ],
+            border_width: 0.0,
+            clear: [ 0.0, 0.0, 0.0, 1.0 ]
+        }
+    }
+
+    /// Everything in shades of one color, like an old monochrome monitor.
+    /// Palette colors keep their brightness
+    pub fn monochrome(fg: [f32; 4], bg: [f32; 4]) -> Self {
+        let palette = Self::classic().palette.iter().map(|color| {
+            let luma = 0.299 * color[0] + 0.587 * color[1] + 0.114 * color[2];
+            [
+                bg[0] + (fg[0] - bg[0]) * luma, bg[1] + (fg[1] - bg[1]) * luma,
+                bg[2] + (fg[2] - bg[2]) * luma, color[3]
+            ]
+        }).collect();
+        Self {
+            palette,
+            fg,
+            bg,
+            border: bg,
+            border_width: 0.0,
+            clear: [ 0.0, 0.0, 0.0, 1.0 ]
+        }
+    }
+
+    /// An amber monochrome monitor
+    pub fn amber() -> Self {
+        Self::monochrome([ 1.0, 0.69, 0.0, 1.0 ], [ 0.05, 0.03, 0.0, 1.0 ])
+    }
+
+    /// A green phosphor monochrome monitor
+    pub fn green_phosphor() -> Self {
+        Self::monochrome([ 0.2, 1.0, 0.3, 1.0 ], [ 0.0, 0.05, 0.01, 1.0 ])
+    }
+
+    /// Replace the start of the palette. Giving 16 colors only changes the ANSI ones
+    pub fn set_palette(&self, palette: &[[f32; 4]]) -> Self {
+        let mut new = self.clone();
+        for (color, new_color) in new.palette.iter_mut().zip(palette) {
+            *color = *new_color;
+        }
+        new
+    }
+
+    /// Change one color of the palette. Indices past the end are ignored
+    pub fn set_palette_color(&self, index: usize, color: [f32; 4]) -> Self {
+        let mut new = self.clone();
+        if let Some(old_color) = new.palette.get_mut(index) {
+            *old_color = color;
+        }
+        new
+    }
+
+    /// Color text is drawn in when it isn't given one
+    pub fn set_fg(&self, fg: [f32; 4]) -> Self {
+        let mut new = self.clone();
+        new.fg = fg;
+        new
+    }
+
+    /// Color behind text when it isn't given one
+    pub fn set_bg(&self, bg: [f32; 4]) -> Self {
+        let mut new = self.clone();
+        new.bg = bg;
+        new
+    }
+
+    /// A frame some pixels wide around the grid. 0 to not have one
+    pub fn set_border(&self, color: [f32; 4], width: f32) -> Self {
+        let mut new = self.clone();
+        new.border = color;
+        new.border_width = width.max(0.0);
+        new
+    }
+
+    /// Color of the window outside the grid and its border
+    pub fn set_clear(&self, clear: [f32; 4]) -> Self {
+        let mut new = self.clone();
+        new.clear = clear;
+        new
+    }
+
+    pub fn palette(&self) -> &[[f32; 4]] {
+        &self.palette
+    }
+
+    pub fn fg(&self) -> [f32; 4] {
+        self.fg
+    }
+
+    pub fn bg(&self) -> [f32; 4] {
+        self.bg
+    }
+
+    /// What a cell's foreground and background look like in this theme
+    pub(crate) fn resolve(&self, (fg, bg): (Color, Color)) -> ([f32; 4], [f32; 4]) {
+        (fg.resolve(&self.palette, self.fg), bg.resolve(&self.palette, self.bg))
+    }
+}
+
+fn rgba(rgb: [u8; 3]) -> [f32; 4] {
+    [ rgb[0] as f32 / 255.0, rgb[1] as f32 / 255.0, rgb[2] as f32 / 255.0, 1.0 ]
+}
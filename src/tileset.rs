@@ -0,0 +1,80 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Bitmap tile sheets in CP437 order, like Dwarf Fortress uses, to draw the grid instead of a font
+
+use std::fs;
+use crate::cp437;
+
+/// Tiles across and down a sheet
+pub(crate) const SHEET_TILES: u32 = 16;
+
+/// A 16x16 sheet of equally sized tiles, one per CP437 character in order.
+/// Sheets without an alpha channel mark their background with magenta, like Dwarf Fortress sets,
+/// or are white on black, like libtcod sets, where brightness is how much of a cell a tile covers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tileset {
+    /// RGBA, with the tiles white where they're drawn so they can be tinted
+    pub(crate) pixels: Vec<u8>,
+    pub(crate) width: u32,
+    pub(crate) height: u32
+}
+
+impl Tileset {
+    /// Decode a sheet from PNG data
+    pub fn from_png(bytes: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        if info.width % SHEET_TILES != 0 || info.height % SHEET_TILES != 0 {
+            Err("Tileset must be 16 tiles across and 16 tiles down.")?;
+        }
+        let buf = &buf[..info.buffer_size()];
+
+        let mut pixels = match info.color_type {
+            png::ColorType::Rgba => buf.to_vec(),
+            png::ColorType::Rgb => buf.chunks(3)
+                .flat_map(|px| [ px[0], px[1], px[2], 255 ])
+                .collect(),
+            png::ColorType::GrayscaleAlpha => buf.chunks(2)
+                .flat_map(|px| [ px[0], px[0], px[0], px[1] ])
+                .collect(),
+            png::ColorType::Grayscale => buf.iter().flat_map(|px| [ *px, *px, *px, 255 ]).collect(),
+            png::ColorType::Indexed => Err("Tileset palette wasn't expanded.")?
+        };
+        if !matches!(info.color_type, png::ColorType::Rgba | png::ColorType::GrayscaleAlpha) {
+            let magenta = |px: &[u8]| px[..3] == [ 255, 0, 255 ];
+            if pixels.chunks(4).any(magenta) {
+                for px in pixels.chunks_mut(4).filter(|px| magenta(px)) {
+                    px.copy_from_slice(&[ 0, 0, 0, 0 ]);
+                }
+            } else {
+                for px in pixels.chunks_mut(4) {
+                    let coverage = px[0].max(px[1]).max(px[2]);
+                    px.copy_from_slice(&[ 255, 255, 255, coverage ]);
+                }
+            }
+        }
+
+        Ok(Self {
+            pixels,
+            width: info.width,
+            height: info.height
+        })
+    }
+
+    /// Read a sheet from a PNG file
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_png(&fs::read(path)?)
+    }
+
+    /// Size of one tile in pixels
+    pub fn tile_size(&self) -> (u32, u32) {
+        (self.width / SHEET_TILES, self.height / SHEET_TILES)
+    }
+
+    /// Which tile draws a character. Characters outside CP437 are drawn as '?'
+    pub(crate) fn tile_for(c: char) -> u32 {
+        cp437::to_byte(c).unwrap_or(b'?') as u32
+    }
+}
@@ -0,0 +1,155 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Delays and repeating ticks kept by the engine, so objects don't have to count delta_time.
+//! Timers run on game time, so they speed up, slow down and pause along with the game
+
+/// Most times a repeating timer goes off in one update. A timer faster than the frame rate, or a
+/// long hitch, would otherwise go off enough times to stall the game catching up
+const MAX_CATCH_UP: usize = 8;
+
+/// What a timer does when it goes off
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TimerAction {
+    /// Call on_timer with the timer's name
+    Event,
+
+    /// Send some text on a channel, through on_message
+    Message(String, String)
+}
+
+/// Something to happen after some seconds, once or over and over. Started with
+/// Command::StartTimer, which replaces any timer with the same name
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timer {
+    pub(crate) name: String,
+    pub(crate) interval: f32,
+    pub(crate) repeating: bool,
+    pub(crate) target: Option<String>,
+    pub(crate) action: TimerAction,
+    pub(crate) elapsed: f32
+}
+
+impl Timer {
+    /// Go off once after some seconds, calling on_timer on every object in play
+    pub fn once(name: &str, delay: f32) -> Self {
+        Self {
+            name: name.to_string(),
+            interval: delay.max(0.0),
+            repeating: false,
+            target: None,
+            action: TimerAction::Event,
+            elapsed: 0.0
+        }
+    }
+
+    /// Go off every so many seconds until cancelled
+    pub fn repeating(name: &str, interval: f32) -> Self {
+        let mut timer = Self::once(name, interval);
+        timer.repeating = true;
+        timer
+    }
+
+    /// Only go off for objects with an id, instead of everything in play
+    pub fn set_target(&self, id: &str) -> Self {
+        let mut new = self.clone();
+        new.target = Some(id.to_string());
+        new
+    }
+
+    /// Send a message on a channel instead of calling on_timer
+    pub fn set_message(&self, channel: &str, text: &str) -> Self {
+        let mut new = self.clone();
+        new.action = TimerAction::Message(channel.to_string(), text.to_string());
+        new
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    pub fn action(&self) -> &TimerAction {
+        &self.action
+    }
+}
+
+/// Every timer running, in the order they were started
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Timers {
+    timers: Vec<Timer>
+}
+
+impl Timers {
+    /// Start a timer, replacing any with the same name
+    pub fn start(&mut self, timer: Timer) {
+        self.cancel(&timer.name);
+        self.timers.push(timer);
+    }
+
+    pub fn cancel(&mut self, name: &str) {
+        self.timers.retain(|timer| timer.name != name);
+    }
+
+    /// Move every timer along, returning the ones going off, once for each time they did, up to
+    /// a limit. One-shot timers that went off are done
+    pub fn update(&mut self, delta_time: f32) -> Vec<Timer> {
+        let mut fired = Vec::new();
+        for timer in self.timers.iter_mut() {
+            timer.elapsed += delta_time;
+            if timer.elapsed < timer.interval {
+                continue;
+            }
+            if !timer.repeating {
+                fired.push(timer.clone());
+            } else if timer.interval > 0.0 {
+                let times = (timer.elapsed / timer.interval) as usize;
+                if times > MAX_CATCH_UP {
+                    // Skip the time it's behind instead of catching all of it up
+                    timer.elapsed = 0.0;
+                } else {
+                    timer.elapsed -= times as f32 * timer.interval;
+                }
+                let times = times.min(MAX_CATCH_UP);
+                fired.extend(std::iter::repeat_n(timer.clone(), times));
+            } else {
+                // Going off every update is as often as it can
+                timer.elapsed = 0.0;
+                fired.push(timer.clone());
+            }
+        }
+        self.timers.retain(|timer| timer.repeating || timer.elapsed < timer.interval);
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn once_goes_off_and_is_done() {
+        let mut timers = Timers::default();
+        timers.start(Timer::once("once", 1.0));
+        assert!(timers.update(0.5).is_empty());
+        assert_eq!(timers.update(0.5).len(), 1);
+        assert!(timers.update(5.0).is_empty());
+    }
+
+    #[test]
+    fn repeating_catches_up() {
+        let mut timers = Timers::default();
+        timers.start(Timer::repeating("tick", 0.25));
+        assert_eq!(timers.update(0.8).len(), 3);
+        assert_eq!(timers.update(0.2).len(), 1);
+    }
+
+    #[test]
+    fn repeating_catch_up_is_capped() {
+        let mut timers = Timers::default();
+        timers.start(Timer::repeating("fast", 1e-9));
+        assert_eq!(timers.update(1.0 / 60.0).len(), MAX_CATCH_UP);
+        assert_eq!(timers.timers[0].elapsed, 0.0);
+    }
+}
@@ -0,0 +1,199 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Effects played when switching rooms, blending the last frame of the old room into the new one
+
+use crate::{
+    canvas::{
+        Canvas, SCREEN_WIDTH, SCREEN_HEIGHT
+    }, color::Color,
+    theme::Theme
+};
+
+/// Cells of fading glyphs trailing behind each drop of rain
+const RAIN_TRAIL: usize = 8;
+/// Furthest into the transition a column of rain can wait before it starts falling
+const RAIN_MAX_DELAY: f32 = 0.4;
+/// Characters rain is made of
+const RAIN_GLYPHS: &[u8] = b"0123456789ABCDEFZ:=*+<>|";
+/// How many times a second rain glyphs change
+const RAIN_FLICKER: f32 = 15.0;
+
+/// How one room turns into the next
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransitionKind {
+    /// Fade the old room to black, then the new one in from black
+    Fade,
+
+    /// Swap cells from the old room to the new one one at a time, in a random order
+    Dissolve,
+
+    /// Sweep the new room in from left to right
+    Wipe,
+
+    /// Rain glyphs down each column, leaving the new room behind them
+    Rain
+}
+
+/// A room switch effect and how long it takes. Input is blocked while it plays unless
+/// set otherwise, so a key held from the old room doesn't do anything in the new one
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transition {
+    pub(crate) kind: TransitionKind,
+    pub(crate) duration: f32,
+    pub(crate) block_input: bool
+}
+
+impl Transition {
+    /// A transition lasting some seconds
+    pub fn new(kind: TransitionKind, duration: f32) -> Self {
+        Self {
+            kind,
+            duration: duration.max(0.0),
+            block_input: true
+        }
+    }
+
+    pub fn fade(duration: f32) -> Self {
+        Self::new(TransitionKind::Fade, duration)
+    }
+
+    pub fn dissolve(duration: f32) -> Self {
+        Self::new(TransitionKind::Dissolve, duration)
+    }
+
+    pub fn wipe(duration: f32) -> Self {
+        Self::new(TransitionKind::Wipe, duration)
+    }
+
+    pub fn rain(duration: f32) -> Self {
+        Self::new(TransitionKind::Rain, duration)
+    }
+
+    /// Whether objects stop getting keys, typed characters and the mouse until it's done
+    pub fn set_block_input(&self, block_input: bool) -> Self {
+        let mut new = *self;
+        new.block_input = block_input;
+        new
+    }
+
+    pub fn kind(&self) -> TransitionKind {
+        self.kind
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.duration
+    }
+}
+
+/// A transition playing, holding onto the old room's last frame
+#[derive(Clone, Debug)]
+pub(crate) struct ActiveTransition {
+    transition: Transition,
+    from: Canvas,
+    elapsed: f32
+}
+
+impl ActiveTransition {
+    pub fn new(transition: Transition, from: Canvas) -> Self {
+        Self {
+            transition,
+            from,
+            elapsed: 0.0
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.elapsed += delta_time;
+    }
+
+    pub fn done(&self) -> bool {
+        self.elapsed >= self.transition.duration
+    }
+
+    pub fn blocks_input(&self) -> bool {
+        self.transition.block_input
+    }
+
+    /// The frame partway between the old room and the new room's frame
+    pub fn composite(&self, to: &Canvas, theme: &Theme) -> Canvas {
+        let t = if self.transition.duration > 0.0 {
+            (self.elapsed / self.transition.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let mut canvas = Canvas::new();
+        for y in 0..SCREEN_HEIGHT {
+            for x in 0..SCREEN_WIDTH {
+                let (c, (fg, bg)) = match self.transition.kind {
+                    TransitionKind::Fade => if t < 0.5 {
+                        dim(&self.from, x, y, 1.0 - t * 2.0, theme)
+                    } else {
+                        dim(to, x, y, t * 2.0 - 1.0, theme)
+                    }, TransitionKind::Dissolve => if noise(x, y, 0) < t {
+                        cell(to, x, y)
+                    } else {
+                        cell(&self.from, x, y)
+                    }, TransitionKind::Wipe => if (x as f32) < t * SCREEN_WIDTH as f32 {
+                        cell(to, x, y)
+                    } else {
+                        cell(&self.from, x, y)
+                    }, TransitionKind::Rain => self.rain(to, x, y, t)
+                };
+                canvas.set_colored(x as i32, y as i32, c, fg, bg);
+            }
+        }
+        canvas
+    }
+
+    /// Each column's drop falls from the top after its own delay, with the new room above its
+    /// trail and the old room still below it
+    fn rain(&self, to: &Canvas, x: usize, y: usize, t: f32) -> (char, (Color, Color)) {
+        let delay = noise(x, 0, 1) * RAIN_MAX_DELAY;
+        let progress = ((t - delay) / (1.0 - RAIN_MAX_DELAY)).clamp(0.0, 1.0);
+        let head = progress * (SCREEN_HEIGHT + RAIN_TRAIL) as f32;
+        let behind = head - y as f32;
+        if behind <= 0.0 {
+            cell(&self.from, x, y)
+        } else if behind > RAIN_TRAIL as f32 {
+            cell(to, x, y)
+        } else {
+            let flicker = (self.elapsed * RAIN_FLICKER) as u32;
+            let glyph = RAIN_GLYPHS[
+                (noise(x, y, flicker + 2) * RAIN_GLYPHS.len() as f32) as usize % RAIN_GLYPHS.len()
+            ] as char;
+            let fg = if behind <= 1.0 {
+                Color::BrightWhite
+            } else {
+                let fade = 1.0 - (behind - 1.0) / RAIN_TRAIL as f32;
+                Color::Rgb(0, (80.0 + 175.0 * fade) as u8, (40.0 * fade) as u8)
+            };
+            (glyph, (fg, Color::Black))
+        }
+    }
+}
+
+fn cell(canvas: &Canvas, x: usize, y: usize) -> (char, (Color, Color)) {
+    (canvas.buffer()[y][x], canvas.color_buffer()[y][x])
+}
+
+/// A cell with its colors darkened toward black, with a brightness from 0 to 1
+fn dim(
+        canvas: &Canvas, x: usize, y: usize,
+        brightness: f32, theme: &Theme) -> (char, (Color, Color)) {
+    let (c, colors) = cell(canvas, x, y);
+    let (fg, bg) = theme.resolve(colors);
+    let scale = |color: [f32; 4]| {
+        let channel = |c: f32| (c.clamp(0.0, 1.0) * brightness * 255.0).round() as u8;
+        Color::Rgb(channel(color[0]), channel(color[1]), channel(color[2]))
+    };
+    (c, (scale(fg), scale(bg)))
+}
+
+/// The same random looking number from 0 to 1 every time for a cell and seed
+fn noise(x: usize, y: usize, seed: u32) -> f32 {
+    let mut hash = (x as u32).wrapping_mul(374_761_393)
+        ^ (y as u32).wrapping_mul(668_265_263)
+        ^ seed.wrapping_mul(2_246_822_519);
+    hash = (hash ^ (hash >> 13)).wrapping_mul(1_274_126_177);
+    hash ^= hash >> 16;
+    hash as f32 / u32::MAX as f32
+}
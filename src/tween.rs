@@ -0,0 +1,433 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Values moving smoothly from one to another over time, like a menu sliding in or a cursor
+//! pulsing. Tweens are played by the engine, which passes each new value to objects through
+//! on_tween, so they only have to copy it over
+
+use std::f32::consts::PI;
+use crate::color::Color;
+
+/// How a tween speeds up and slows down between its start and end.
+/// In curves start slow, Out curves end slow and InOut curves do both
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    ExpoIn,
+    ExpoOut,
+    ExpoInOut,
+    /// Pull back a little before going
+    BackIn,
+    /// Overshoot a little and come back
+    BackOut,
+    BackInOut,
+    /// Wobble like a spring
+    ElasticIn,
+    ElasticOut,
+    /// Bounce like a dropped ball
+    BounceIn,
+    BounceOut
+}
+
+impl Easing {
+    /// How far along the value is, usually from 0 to 1, when the time is t of the way through
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        let back = 1.70158;
+        let elastic = 2.0 * PI / 3.0;
+        match self {
+            Self::Linear => t,
+            Self::QuadIn => t * t,
+            Self::QuadOut => 1.0 - (1.0 - t).powi(2),
+            Self::QuadInOut => if t < 0.5 {
+                2.0 * t * t
+            } else {
+                1.0 - (2.0 - 2.0 * t).powi(2) / 2.0
+            }, Self::CubicIn => t.powi(3),
+            Self::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Self::CubicInOut => if t < 0.5 {
+                4.0 * t.powi(3)
+            } else {
+                1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+            }, Self::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Self::SineOut => (t * PI / 2.0).sin(),
+            Self::SineInOut => (1.0 - (t * PI).cos()) / 2.0,
+            Self::ExpoIn => if t == 0.0 {
+                0.0
+            } else {
+                2f32.powf(10.0 * t - 10.0)
+            }, Self::ExpoOut => if t == 1.0 {
+                1.0
+            } else {
+                1.0 - 2f32.powf(-10.0 * t)
+            }, Self::ExpoInOut => if t == 0.0 || t == 1.0 {
+                t
+            } else if t < 0.5 {
+                2f32.powf(20.0 * t - 10.0) / 2.0
+            } else {
+                (2.0 - 2f32.powf(10.0 - 20.0 * t)) / 2.0
+            }, Self::BackIn => (back + 1.0) * t.powi(3) - back * t * t,
+            Self::BackOut => 1.0 + (back + 1.0) * (t - 1.0).powi(3) + back * (t - 1.0).powi(2),
+            Self::BackInOut => {
+                let back = back * 1.525;
+                if t < 0.5 {
+                    (2.0 * t).powi(2) * ((back + 1.0) * 2.0 * t - back) / 2.0
+                } else {
+                    ((2.0 * t - 2.0).powi(2) * ((back + 1.0) * (2.0 * t - 2.0) + back) + 2.0) / 2.0
+                }
+            }, Self::ElasticIn => if t == 0.0 || t == 1.0 {
+                t
+            } else {
+                -2f32.powf(10.0 * t - 10.0) * ((10.0 * t - 10.75) * elastic).sin()
+            }, Self::ElasticOut => if t == 0.0 || t == 1.0 {
+                t
+            } else {
+                2f32.powf(-10.0 * t) * ((10.0 * t - 0.75) * elastic).sin() + 1.0
+            }, Self::BounceIn => 1.0 - bounce_out(1.0 - t),
+            Self::BounceOut => bounce_out(t)
+        }
+    }
+}
+
+fn bounce_out(t: f32) -> f32 {
+    let (n, d) = (7.5625, 2.75);
+    if t < 1.0 / d {
+        n * t * t
+    } else if t < 2.0 / d {
+        let t = t - 1.5 / d;
+        n * t * t + 0.75
+    } else if t < 2.5 / d {
+        let t = t - 2.25 / d;
+        n * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / d;
+        n * t * t + 0.984375
+    }
+}
+
+/// Something a tween can move: a number, a position or size, or an RGBA color
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TweenValue {
+    Float(f32),
+    Vector(f32, f32),
+    Color([f32; 4])
+}
+
+impl TweenValue {
+    pub fn as_float(&self) -> Option<f32> {
+        match self {
+            Self::Float(value) => Some(*value),
+            _ => None
+        }
+    }
+
+    pub fn as_vector(&self) -> Option<(f32, f32)> {
+        match self {
+            Self::Vector(x, y) => Some((*x, *y)),
+            _ => None
+        }
+    }
+
+    /// A vector rounded to the nearest cell
+    pub fn as_cell(&self) -> Option<(i32, i32)> {
+        self.as_vector().map(|(x, y)| (x.round() as i32, y.round() as i32))
+    }
+
+    /// A color ready to draw cells with
+    pub fn as_color(&self) -> Option<Color> {
+        match self {
+            Self::Color(color) => {
+                let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+                Some(Color::Rgb(channel(color[0]), channel(color[1]), channel(color[2])))
+            }, _ => None
+        }
+    }
+
+    /// Partway from this value to another. Values of different kinds can't be blended, so
+    /// they jump over at the end
+    fn lerp(&self, other: &Self, amount: f32) -> Self {
+        let mix = |from: f32, to: f32| from + (to - from) * amount;
+        match (self, other) {
+            (Self::Float(from), Self::Float(to)) => Self::Float(mix(*from, *to)),
+            (Self::Vector(from_x, from_y), Self::Vector(to_x, to_y)) =>
+                Self::Vector(mix(*from_x, *to_x), mix(*from_y, *to_y)),
+            (Self::Color(from), Self::Color(to)) => Self::Color([
+                mix(from[0], to[0]), mix(from[1], to[1]), mix(from[2], to[2]), mix(from[3], to[3])
+            ]), _ => if amount < 1.0 {
+                *self
+            } else {
+                *other
+            }
+        }
+    }
+}
+
+/// A property of an object moving between two values, or tweens put together.
+/// Started with Command::StartTween
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tween {
+    /// A named property going from one value to another over some seconds
+    Value {
+        property: String,
+        from: TweenValue,
+        to: TweenValue,
+        duration: f32,
+        easing: Easing
+    },
+
+    /// Nothing happening for some seconds, for spacing out a sequence
+    Delay(f32),
+
+    /// Tweens played one after another
+    Sequence(Vec<Tween>),
+
+    /// Tweens played all at once, lasting as long as the longest
+    Parallel(Vec<Tween>),
+
+    /// A tween played over and over until it's stopped, like a pulsing cursor
+    Loop(Box<Tween>)
+}
+
+impl Tween {
+    pub fn float(property: &str, from: f32, to: f32, duration: f32, easing: Easing) -> Self {
+        Self::value(property, TweenValue::Float(from), TweenValue::Float(to), duration, easing)
+    }
+
+    pub fn vector(
+            property: &str, from: (f32, f32), to: (f32, f32),
+            duration: f32, easing: Easing) -> Self {
+        Self::value(
+            property,
+            TweenValue::Vector(from.0, from.1), TweenValue::Vector(to.0, to.1),
+            duration, easing
+        )
+    }
+
+    pub fn color(
+            property: &str, from: [f32; 4], to: [f32; 4],
+            duration: f32, easing: Easing) -> Self {
+        Self::value(property, TweenValue::Color(from), TweenValue::Color(to), duration, easing)
+    }
+
+    pub fn delay(duration: f32) -> Self {
+        Self::Delay(duration.max(0.0))
+    }
+
+    pub fn sequence(tweens: &[Tween]) -> Self {
+        Self::Sequence(tweens.to_vec())
+    }
+
+    pub fn parallel(tweens: &[Tween]) -> Self {
+        Self::Parallel(tweens.to_vec())
+    }
+
+    pub fn repeat_forever(tween: &Tween) -> Self {
+        Self::Loop(Box::new(tween.clone()))
+    }
+
+    fn value(
+            property: &str, from: TweenValue, to: TweenValue,
+            duration: f32, easing: Easing) -> Self {
+        Self::Value {
+            property: property.to_string(),
+            from,
+            to,
+            duration: duration.max(0.0),
+            easing
+        }
+    }
+
+    /// Seconds from start to finish
+    pub fn duration(&self) -> f32 {
+        match self {
+            Self::Value { duration, .. } | Self::Delay(duration) => *duration,
+            Self::Sequence(tweens) => tweens.iter().map(|tween| tween.duration()).sum(),
+            Self::Parallel(tweens) => tweens.iter()
+                .map(|tween| tween.duration())
+                .fold(0.0, f32::max),
+            Self::Loop(_) => f32::INFINITY
+        }
+    }
+
+    /// Values of every property that was moving some time after prev up to time.
+    /// Properties that finished in that time get their end value
+    fn values(&self, prev: f32, time: f32, values: &mut Vec<(String, TweenValue)>) {
+        match self {
+            Self::Value { property, from, to, duration, easing } => {
+                if time < 0.0 || *duration <= prev {
+                    return;
+                }
+                let t = if *duration > 0.0 {
+                    time / duration
+                } else {
+                    1.0
+                };
+                let amount = if t >= 1.0 {
+                    1.0
+                } else {
+                    easing.apply(t)
+                };
+                values.push((property.clone(), from.lerp(to, amount)));
+            }, Self::Delay(_) => {},
+            Self::Sequence(tweens) => {
+                let mut start = 0.0;
+                for tween in tweens {
+                    tween.values(prev - start, time - start, values);
+                    start += tween.duration();
+                }
+            }, Self::Parallel(tweens) => for tween in tweens {
+                tween.values(prev, time, values);
+            }, Self::Loop(tween) => {
+                let duration = tween.duration();
+                if duration <= 0.0 || duration.is_infinite() || time < 0.0 {
+                    return;
+                }
+                // Only the current time around matters. Starting a new one counts from its start
+                let start = (time / duration).floor() * duration;
+                tween.values(prev - start, time - start, values);
+            }
+        }
+    }
+}
+
+/// A tween being played under a name, for an object with an id or everything in play
+#[derive(Clone, Debug)]
+struct PlayingTween {
+    name: String,
+    target: Option<String>,
+    tween: Tween,
+    elapsed: f32
+}
+
+/// What a tween did over an update
+#[derive(Clone, Debug)]
+pub(crate) struct TweenFrame {
+    pub name: String,
+    pub target: Option<String>,
+    pub values: Vec<(String, TweenValue)>,
+    pub finished: bool
+}
+
+/// Every tween playing, in the order they were started
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Tweens {
+    tweens: Vec<PlayingTween>
+}
+
+impl Tweens {
+    /// Start a tween, replacing any with the same name
+    pub fn start(&mut self, name: &str, target: Option<String>, tween: Tween) {
+        self.stop(name);
+        self.tweens.push(PlayingTween {
+            name: name.to_string(),
+            target,
+            tween,
+            elapsed: 0.0
+        });
+    }
+
+    pub fn stop(&mut self, name: &str) {
+        self.tweens.retain(|tween| tween.name != name);
+    }
+
+    /// Move every tween along, returning their new values. Finished tweens are dropped
+    pub fn update(&mut self, delta_time: f32) -> Vec<TweenFrame> {
+        let mut frames = Vec::new();
+        for playing in self.tweens.iter_mut() {
+            // Nothing's been played before the first update, so start from just before 0
+            let prev = if playing.elapsed > 0.0 {
+                playing.elapsed
+            } else {
+                -1.0
+            };
+            playing.elapsed += delta_time;
+            let mut values = Vec::new();
+            playing.tween.values(prev, playing.elapsed, &mut values);
+            frames.push(TweenFrame {
+                name: playing.name.clone(),
+                target: playing.target.clone(),
+                values,
+                finished: playing.elapsed >= playing.tween.duration()
+            });
+        }
+        self.tweens.retain(|playing| playing.elapsed < playing.tween.duration());
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(tween: &Tween, prev: f32, time: f32) -> Vec<(String, TweenValue)> {
+        let mut values = Vec::new();
+        tween.values(prev, time, &mut values);
+        values
+    }
+
+    fn float(property: &str, value: f32) -> (String, TweenValue) {
+        (property.to_string(), TweenValue::Float(value))
+    }
+
+    fn two_steps() -> Tween {
+        Tween::sequence(&[
+            Tween::float("a", 0.0, 1.0, 1.0, Easing::Linear),
+            Tween::delay(0.5),
+            Tween::float("b", 0.0, 2.0, 1.0, Easing::Linear)
+        ])
+    }
+
+    #[test]
+    fn sequence_boundaries() {
+        let tween = two_steps();
+        assert_eq!(tween.duration(), 2.5);
+        assert_eq!(values(&tween, -1.0, 0.0), vec![ float("a", 0.0) ]);
+        assert_eq!(values(&tween, 0.5, 1.0), vec![ float("a", 1.0) ]);
+        assert_eq!(values(&tween, 1.0, 1.5), vec![ float("b", 0.0) ]);
+        assert_eq!(values(&tween, 1.5, 2.0), vec![ float("b", 1.0) ]);
+    }
+
+    #[test]
+    fn sequence_skipped_over_ends_each_step() {
+        let tween = two_steps();
+        assert_eq!(values(&tween, 0.5, 3.0), vec![ float("a", 1.0), float("b", 2.0) ]);
+        assert!(values(&tween, 3.0, 4.0).is_empty());
+    }
+
+    #[test]
+    fn loop_boundaries() {
+        let tween = Tween::repeat_forever(&Tween::float("a", 0.0, 1.0, 1.0, Easing::Linear));
+        assert_eq!(values(&tween, 0.5, 0.75), vec![ float("a", 0.75) ]);
+        assert_eq!(values(&tween, 0.75, 1.0), vec![ float("a", 0.0) ]);
+        assert_eq!(values(&tween, 2.75, 3.25), vec![ float("a", 0.25) ]);
+        assert!(values(&Tween::repeat_forever(&Tween::delay(0.0)), 0.0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn loop_of_sequence() {
+        let tween = Tween::repeat_forever(&two_steps());
+        assert_eq!(values(&tween, 2.25, 2.75), vec![ float("a", 0.25) ]);
+        assert_eq!(values(&tween, 4.0, 4.5), vec![ float("b", 1.0) ]);
+    }
+
+    #[test]
+    fn playing_reports_start_and_finish() {
+        let mut tweens = Tweens::default();
+        tweens.start("slide", None, Tween::float("x", 0.0, 1.0, 1.0, Easing::QuadIn));
+        let frame = &tweens.update(0.0)[0];
+        assert_eq!(frame.values, vec![ float("x", 0.0) ]);
+        assert!(!frame.finished);
+        let frame = &tweens.update(2.0)[0];
+        assert_eq!(frame.values, vec![ float("x", 1.0) ]);
+        assert!(frame.finished);
+        assert!(tweens.update(1.0).is_empty());
+    }
+}
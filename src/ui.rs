@@ -0,0 +1,1137 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Menus, buttons, fields and other widgets, grouped into panels that handle focus and input
+
+use std::{
+    collections::HashMap,
+    sync::Arc
+};
+use winit::event::{
+    VirtualKeyCode, MouseButton
+};
+use crate::{
+    cmd::{
+        self, Command
+    }, obj::{
+        GameObject, MouseEvent
+    }, canvas::{
+        Canvas, BoxStyle
+    }, color::Color,
+    text::{
+        self, Align
+    }
+};
+
+/// Characters and colors widgets are drawn with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UiTheme {
+    pub frame: BoxStyle,
+    /// Drawn either side of the focused widget, and beside the selected menu item
+    pub focus_left: char,
+    pub focus_right: char,
+    pub check_on: char,
+    pub check_off: char,
+    pub slider_track: char,
+    pub slider_knob: char,
+    pub bar_full: char,
+    pub bar_empty: char,
+    pub text_cursor: char,
+    /// Fills the inside of a panel's frame, hiding what's under it
+    pub fill: char,
+    /// Colors of a framed panel and everything drawn in it
+    pub fg: Color,
+    pub bg: Color
+}
+
+impl Default for UiTheme {
+    fn default() -> Self {
+        Self {
+            frame: BoxStyle::ASCII,
+            focus_left: '>',
+            focus_right: '<',
+            check_on: 'x',
+            check_off: ' ',
+            slider_track: '-',
+            slider_knob: 'O',
+            bar_full: '#',
+            bar_empty: '.',
+            text_cursor: '_',
+            fill: ' ',
+            fg: Color::Default,
+            bg: Color::Default
+        }
+    }
+}
+
+impl UiTheme {
+    /// What a widget's edges are drawn with, showing whether it has focus
+    pub fn brackets(&self, focused: bool) -> (char, char) {
+        if focused {
+            (self.focus_left, self.focus_right)
+        } else {
+            ('[', ']')
+        }
+    }
+}
+
+/// What widget callbacks can change: the room, like other callbacks, and whether the panel
+/// they're in should close
+pub struct UiContext<'a> {
+    pub cur_room: &'a mut String,
+    pub close: bool
+}
+
+type Action = Arc<dyn Fn(&mut UiContext) + Send + Sync>;
+type ValueAction<T> = Arc<dyn Fn(T, &mut UiContext) + Send + Sync>;
+
+/// Something drawn and interacted with inside a panel. Positions are in screen cells
+pub trait Widget: WidgetClone + Send + Sync {
+    /// Left, top, width and height on screen, for mouse input
+    fn rect(&self) -> (i32, i32, usize, usize);
+
+    fn draw(&self, canvas: &mut Canvas, theme: &UiTheme, focused: bool);
+
+    /// Whether focus can move here
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    /// Handle a key while focused. Return true if it was used, so the panel won't also use it
+    fn on_key(&mut self, _code: VirtualKeyCode, _ctx: &mut UiContext) -> bool {
+        false
+    }
+
+    /// Handle a typed character while focused
+    fn on_char(&mut self, _c: char, _ctx: &mut UiContext) {}
+
+    /// Handle the mouse while it's over the widget
+    fn on_mouse(&mut self, _event: MouseEvent, _ctx: &mut UiContext) {}
+
+    /// Handle a message sent to every object, which panels pass on to all their widgets
+    fn on_message(&mut self, _channel: &str, _text: &str) {}
+
+    fn contains(&self, pos: (i32, i32)) -> bool {
+        let (x, y, width, height) = self.rect();
+        (x..x + width as i32).contains(&pos.0) && (y..y + height as i32).contains(&pos.1)
+    }
+}
+
+/// Allows us to store Widgets in Vecs
+pub trait WidgetClone {
+    fn clone_box(&self) -> Box<dyn Widget>;
+}
+
+impl<T> WidgetClone for T where T: 'static + Widget + Clone {
+    fn clone_box(&self) -> Box<dyn Widget> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn Widget> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+fn is_activate_key(code: VirtualKeyCode) -> bool {
+    matches!(code, VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter | VirtualKeyCode::Space)
+}
+
+/// Text that can't be focused
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Label {
+    pos: (i32, i32),
+    width: usize,
+    text: String,
+    align: Align
+}
+
+impl Label {
+    pub fn new(pos: (i32, i32), width: usize, text: &str, align: Align) -> Self {
+        Self {
+            pos,
+            width,
+            text: text.to_string(),
+            align
+        }
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+    }
+}
+
+impl Widget for Label {
+    fn rect(&self) -> (i32, i32, usize, usize) {
+        (self.pos.0, self.pos.1, self.width, 1)
+    }
+
+    fn draw(&self, canvas: &mut Canvas, _theme: &UiTheme, _focused: bool) {
+        canvas.print_aligned(self.pos.0, self.pos.1, self.width, &self.text, self.align);
+    }
+
+    fn focusable(&self) -> bool {
+        false
+    }
+}
+
+/// Runs an action when pressed with enter, space or a click
+#[derive(Clone)]
+pub struct Button {
+    pos: (i32, i32),
+    label: String,
+    on_press: Action
+}
+
+impl Button {
+    pub fn new<F>(pos: (i32, i32), label: &str, on_press: F) -> Self
+            where F: 'static + Fn(&mut UiContext) + Send + Sync {
+        Self {
+            pos,
+            label: label.to_string(),
+            on_press: Arc::new(on_press)
+        }
+    }
+}
+
+impl Widget for Button {
+    fn rect(&self) -> (i32, i32, usize, usize) {
+        (self.pos.0, self.pos.1, self.label.chars().count() + 4, 1)
+    }
+
+    fn draw(&self, canvas: &mut Canvas, theme: &UiTheme, focused: bool) {
+        let (left, right) = theme.brackets(focused);
+        canvas.print_str(self.pos.0, self.pos.1, &format!("{} {} {}", left, self.label, right));
+    }
+
+    fn on_key(&mut self, code: VirtualKeyCode, ctx: &mut UiContext) -> bool {
+        if is_activate_key(code) {
+            (self.on_press)(ctx);
+            return true;
+        }
+        false
+    }
+
+    fn on_mouse(&mut self, event: MouseEvent, ctx: &mut UiContext) {
+        if let MouseEvent::Pressed(MouseButton::Left, _) = event {
+            (self.on_press)(ctx);
+        }
+    }
+}
+
+/// A list of choices, scrolled to keep the selected one in view. Runs an action on the
+/// chosen item's index when it's picked with enter, space or a click
+#[derive(Clone)]
+pub struct Menu {
+    pos: (i32, i32),
+    size: (usize, usize),
+    items: Vec<String>,
+    selected: usize,
+    scroll: usize,
+    on_select: ValueAction<usize>
+}
+
+impl Menu {
+    pub fn new<F>(pos: (i32, i32), size: (usize, usize), items: &[&str], on_select: F) -> Self
+            where F: 'static + Fn(usize, &mut UiContext) + Send + Sync {
+        Self {
+            pos,
+            size,
+            items: items.iter().map(|item| item.to_string()).collect(),
+            selected: 0,
+            scroll: 0,
+            on_select: Arc::new(on_select)
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.items.len().saturating_sub(1));
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + self.size.1 {
+            self.scroll = self.selected + 1 - self.size.1;
+        }
+    }
+
+    /// Which item is drawn on a row of the screen
+    fn item_at(&self, y: i32) -> Option<usize> {
+        let index = self.scroll + (y - self.pos.1) as usize;
+        (index < self.items.len()).then_some(index)
+    }
+}
+
+impl Widget for Menu {
+    fn rect(&self) -> (i32, i32, usize, usize) {
+        (self.pos.0, self.pos.1, self.size.0, self.size.1)
+    }
+
+    fn draw(&self, canvas: &mut Canvas, theme: &UiTheme, focused: bool) {
+        let visible = self.items.iter().enumerate().skip(self.scroll).take(self.size.1);
+        for (row, (index, item)) in visible.enumerate() {
+            let marker = if index == self.selected && focused { theme.focus_left } else { ' ' };
+            canvas.print_str(self.pos.0, self.pos.1 + row as i32, &marker.to_string());
+            canvas.print_aligned(
+                self.pos.0 + 2, self.pos.1 + row as i32, self.size.0.saturating_sub(2),
+                item, Align::Left
+            );
+        }
+    }
+
+    fn on_key(&mut self, code: VirtualKeyCode, ctx: &mut UiContext) -> bool {
+        match code {
+            // Let focus move on past the ends of the list
+            VirtualKeyCode::Up if self.selected > 0 => self.select(self.selected - 1),
+            VirtualKeyCode::Down if self.selected + 1 < self.items.len() =>
+                self.select(self.selected + 1),
+            VirtualKeyCode::PageUp => self.select(self.selected.saturating_sub(self.size.1)),
+            VirtualKeyCode::PageDown => self.select(self.selected + self.size.1),
+            VirtualKeyCode::Home => self.select(0),
+            VirtualKeyCode::End => self.select(self.items.len()),
+            code if is_activate_key(code) && !self.items.is_empty() =>
+                (self.on_select)(self.selected, ctx),
+            _ => return false
+        }
+        true
+    }
+
+    fn on_mouse(&mut self, event: MouseEvent, ctx: &mut UiContext) {
+        match event {
+            MouseEvent::Moved((_, y)) => {
+                if let Some(index) = self.item_at(y) {
+                    self.selected = index;
+                }
+            }, MouseEvent::Pressed(MouseButton::Left, (_, y)) => {
+                if let Some(index) = self.item_at(y) {
+                    self.selected = index;
+                    (self.on_select)(index, ctx);
+                }
+            }, MouseEvent::Scrolled(lines, _) => {
+                let max_scroll = self.items.len().saturating_sub(self.size.1);
+                self.scroll = (self.scroll as i32 - lines).clamp(0, max_scroll as i32) as usize;
+            }, _ => {}
+        }
+    }
+}
+
+/// A single line of typed text. Runs an action with the text when enter is pressed
+#[derive(Clone)]
+pub struct TextInput {
+    pos: (i32, i32),
+    width: usize,
+    text: String,
+    max_len: usize,
+    on_submit: ValueAction<String>
+}
+
+impl TextInput {
+    pub fn new<F>(pos: (i32, i32), width: usize, max_len: usize, on_submit: F) -> Self
+            where F: 'static + Fn(String, &mut UiContext) + Send + Sync {
+        Self {
+            pos,
+            width,
+            text: String::new(),
+            max_len,
+            on_submit: Arc::new(on_submit)
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.chars().take(self.max_len).collect();
+    }
+}
+
+impl Widget for TextInput {
+    fn rect(&self) -> (i32, i32, usize, usize) {
+        (self.pos.0, self.pos.1, self.width + 2, 1)
+    }
+
+    fn draw(&self, canvas: &mut Canvas, theme: &UiTheme, focused: bool) {
+        // Show the end of the text, where typing happens
+        let mut shown = self.text.chars().collect::<Vec<_>>();
+        if focused {
+            shown.push(theme.text_cursor);
+        }
+        let start = shown.len().saturating_sub(self.width);
+        let shown = shown[start..].iter().collect::<String>();
+        let (left, right) = theme.brackets(focused);
+        let field = format!("{}{:<width$}{}", left, shown, right, width = self.width);
+        canvas.print_str(self.pos.0, self.pos.1, &field);
+    }
+
+    fn on_key(&mut self, code: VirtualKeyCode, ctx: &mut UiContext) -> bool {
+        match code {
+            VirtualKeyCode::Back => {
+                self.text.pop();
+            }, VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter =>
+                (self.on_submit)(self.text.clone(), ctx),
+            // Typing a space comes through on_char, so don't let the panel take it
+            VirtualKeyCode::Space => {},
+            _ => return false
+        }
+        true
+    }
+
+    fn on_char(&mut self, c: char, _ctx: &mut UiContext) {
+        if self.text.chars().count() < self.max_len {
+            self.text.push(c);
+        }
+    }
+}
+
+/// An on/off box with a label. Runs an action with the new state when toggled
+#[derive(Clone)]
+pub struct Checkbox {
+    pos: (i32, i32),
+    label: String,
+    checked: bool,
+    on_toggle: ValueAction<bool>
+}
+
+impl Checkbox {
+    pub fn new<F>(pos: (i32, i32), label: &str, checked: bool, on_toggle: F) -> Self
+            where F: 'static + Fn(bool, &mut UiContext) + Send + Sync {
+        Self {
+            pos,
+            label: label.to_string(),
+            checked,
+            on_toggle: Arc::new(on_toggle)
+        }
+    }
+
+    pub fn checked(&self) -> bool {
+        self.checked
+    }
+
+    fn toggle(&mut self, ctx: &mut UiContext) {
+        self.checked = !self.checked;
+        (self.on_toggle)(self.checked, ctx);
+    }
+}
+
+impl Widget for Checkbox {
+    fn rect(&self) -> (i32, i32, usize, usize) {
+        (self.pos.0, self.pos.1, self.label.chars().count() + 4, 1)
+    }
+
+    fn draw(&self, canvas: &mut Canvas, theme: &UiTheme, focused: bool) {
+        let mark = if self.checked { theme.check_on } else { theme.check_off };
+        let label = if focused {
+            format!("{} {}", self.label, theme.focus_right)
+        } else {
+            self.label.clone()
+        };
+        canvas.print_str(self.pos.0, self.pos.1, &format!("[{}] {}", mark, label));
+    }
+
+    fn on_key(&mut self, code: VirtualKeyCode, ctx: &mut UiContext) -> bool {
+        if is_activate_key(code) {
+            self.toggle(ctx);
+            return true;
+        }
+        false
+    }
+
+    fn on_mouse(&mut self, event: MouseEvent, ctx: &mut UiContext) {
+        if let MouseEvent::Pressed(MouseButton::Left, _) = event {
+            self.toggle(ctx);
+        }
+    }
+}
+
+/// A value picked from a range by sliding a knob with the arrow keys or mouse.
+/// Runs an action with the new value when it changes
+#[derive(Clone)]
+pub struct Slider {
+    pos: (i32, i32),
+    width: usize,
+    range: (f32, f32),
+    step: f32,
+    value: f32,
+    on_change: ValueAction<f32>
+}
+
+impl Slider {
+    /// A slider over a range given either way around. A bound that isn't a number is left out
+    pub fn new<F>(
+            pos: (i32, i32), width: usize, range: (f32, f32), step: f32,
+            value: f32, on_change: F) -> Self
+            where F: 'static + Fn(f32, &mut UiContext) + Send + Sync {
+        // min and max skip over NaN, so it's only NaN if both are
+        let low = range.0.min(range.1);
+        let high = range.0.max(range.1);
+        let range = if low.is_nan() { (0.0, 0.0) } else { (low, high) };
+        let value = if value.is_nan() { range.0 } else { value.clamp(range.0, range.1) };
+        Self {
+            pos,
+            width: width.max(2),
+            range,
+            step,
+            value,
+            on_change: Arc::new(on_change)
+        }
+    }
+
+    pub fn value(&self) -> f32 {
+        self.value
+    }
+
+    fn set_value(&mut self, value: f32, ctx: &mut UiContext) {
+        let value = value.clamp(self.range.0, self.range.1);
+        if value != self.value {
+            self.value = value;
+            (self.on_change)(value, ctx);
+        }
+    }
+
+    fn knob_x(&self) -> usize {
+        let span = self.range.1 - self.range.0;
+        if span <= 0.0 {
+            return 0;
+        }
+        (((self.value - self.range.0) / span) * (self.width - 1) as f32).round() as usize
+    }
+}
+
+impl Widget for Slider {
+    fn rect(&self) -> (i32, i32, usize, usize) {
+        (self.pos.0, self.pos.1, self.width + 2, 1)
+    }
+
+    fn draw(&self, canvas: &mut Canvas, theme: &UiTheme, focused: bool) {
+        let (left, right) = theme.brackets(focused);
+        let track = (0..self.width)
+            .map(|x| if x == self.knob_x() { theme.slider_knob } else { theme.slider_track })
+            .collect::<String>();
+        canvas.print_str(self.pos.0, self.pos.1, &format!("{}{}{}", left, track, right));
+    }
+
+    fn on_key(&mut self, code: VirtualKeyCode, ctx: &mut UiContext) -> bool {
+        match code {
+            VirtualKeyCode::Left => self.set_value(self.value - self.step, ctx),
+            VirtualKeyCode::Right => self.set_value(self.value + self.step, ctx),
+            VirtualKeyCode::Home => self.set_value(self.range.0, ctx),
+            VirtualKeyCode::End => self.set_value(self.range.1, ctx),
+            _ => return false
+        }
+        true
+    }
+
+    fn on_mouse(&mut self, event: MouseEvent, ctx: &mut UiContext) {
+        if let MouseEvent::Pressed(MouseButton::Left, (x, _)) = event {
+            let along = (x - self.pos.0 - 1).clamp(0, self.width as i32 - 1) as f32;
+            let span = self.range.1 - self.range.0;
+            self.set_value(self.range.0 + span * along / (self.width - 1) as f32, ctx);
+        }
+    }
+}
+
+/// How full something is, from 0 to 1. Give it a source to read the value from every frame
+#[derive(Clone)]
+pub struct ProgressBar {
+    pos: (i32, i32),
+    width: usize,
+    progress: f32,
+    source: Option<Arc<dyn Fn() -> f32 + Send + Sync>>
+}
+
+impl ProgressBar {
+    pub fn new(pos: (i32, i32), width: usize, progress: f32) -> Self {
+        Self {
+            pos,
+            width,
+            progress: progress.clamp(0.0, 1.0),
+            source: None
+        }
+    }
+
+    /// Read the progress from somewhere else, like a loading counter, instead of a fixed value
+    pub fn set_source<F>(&self, source: F) -> Self
+            where F: 'static + Fn() -> f32 + Send + Sync {
+        let mut new = self.clone();
+        new.source = Some(Arc::new(source));
+        new
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.source.as_ref().map(|source| source().clamp(0.0, 1.0)).unwrap_or(self.progress)
+    }
+
+    pub fn set_progress(&mut self, progress: f32) {
+        self.progress = progress.clamp(0.0, 1.0);
+    }
+}
+
+impl Widget for ProgressBar {
+    fn rect(&self) -> (i32, i32, usize, usize) {
+        (self.pos.0, self.pos.1, self.width, 1)
+    }
+
+    fn draw(&self, canvas: &mut Canvas, theme: &UiTheme, _focused: bool) {
+        let full = (self.progress() * self.width as f32).round() as usize;
+        let bar = (0..self.width)
+            .map(|x| if x < full { theme.bar_full } else { theme.bar_empty })
+            .collect::<String>();
+        canvas.print_str(self.pos.0, self.pos.1, &bar);
+    }
+
+    fn focusable(&self) -> bool {
+        false
+    }
+}
+
+/// The channel message logs listen on unless they're given another
+pub const LOG_CHANNEL: &str = "log";
+
+/// Send a line to every message log listening on the default channel
+pub fn log_message(text: &str) {
+    cmd::send(Command::Message(LOG_CHANNEL.to_string(), text.to_string()));
+}
+
+/// Lines of text, newest at the bottom, that can be scrolled back through when focused or with
+/// the mouse wheel. Long messages are word wrapped, and a message repeating the last one is
+/// counted instead of added again, like "You hit the rat x3".
+/// It stays scrolled to the newest message unless it's been scrolled back
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageLog {
+    pos: (i32, i32),
+    size: (usize, usize),
+    /// Each message, its color and how many times in a row it came
+    messages: Vec<(String, Color, usize)>,
+    history: usize,
+    channel: String,
+    /// What messages from the channel are drawn in
+    color: Color,
+    /// Lines scrolled up from the newest
+    scroll: usize
+}
+
+impl MessageLog {
+    /// A log keeping up to history messages, listening for them on LOG_CHANNEL
+    pub fn new(pos: (i32, i32), size: (usize, usize), history: usize) -> Self {
+        Self {
+            pos,
+            size,
+            messages: Vec::new(),
+            history,
+            channel: LOG_CHANNEL.to_string(),
+            color: Color::Default,
+            scroll: 0
+        }
+    }
+
+    /// Listen for messages on another channel, like for a separate combat log
+    pub fn set_channel(&self, channel: &str) -> Self {
+        let mut new = self.clone();
+        new.channel = channel.to_string();
+        new
+    }
+
+    /// Draw messages from the channel in a color, like red for a combat log
+    pub fn set_color(&self, color: Color) -> Self {
+        let mut new = self.clone();
+        new.color = color;
+        new
+    }
+
+    /// Add a message, dropping the oldest if there are too many
+    pub fn push(&mut self, message: &str) {
+        self.push_colored(message, Color::Default);
+    }
+
+    /// Add a message in a color, like red for damage taken. Default keeps the log's colors.
+    /// It's only counted as a repeat of the last message if that was the same color too
+    pub fn push_colored(&mut self, message: &str, color: Color) {
+        let old_len = self.lines().len();
+        match self.messages.last_mut() {
+            Some((last, last_color, count)) if last == message && *last_color == color =>
+                *count += 1,
+            _ => self.messages.push((message.to_string(), color, 1))
+        }
+        // Keep what's being read in place. Only lines added at the bottom move it
+        let added = self.lines().len().saturating_sub(old_len);
+        if self.messages.len() > self.history {
+            self.messages.remove(0);
+        }
+        if self.scroll > 0 {
+            self.scroll_by(added as i32);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+        self.scroll = 0;
+    }
+
+    /// The messages kept, oldest first, with repeat counts
+    pub fn messages(&self) -> Vec<String> {
+        self.messages.iter().map(|(message, _, count)| match count {
+            1 => message.clone(),
+            _ => format!("{} x{}", message, count)
+        }).collect()
+    }
+
+    pub(crate) fn set_rect(&mut self, pos: (i32, i32), size: (usize, usize)) {
+        self.pos = pos;
+        self.size = size;
+    }
+
+    /// Wrapped lines, each in its message's color
+    fn lines(&self) -> Vec<(String, Color)> {
+        self.messages().iter().zip(&self.messages).flat_map(|(message, (_, color, _))| {
+            text::wrap(message, self.size.0).into_iter().map(move |line| (line, *color))
+        }).collect()
+    }
+
+    fn scroll_by(&mut self, lines: i32) {
+        let max_scroll = self.lines().len().saturating_sub(self.size.1);
+        self.scroll = (self.scroll as i32 + lines).clamp(0, max_scroll as i32) as usize;
+    }
+}
+
+impl Widget for MessageLog {
+    fn rect(&self) -> (i32, i32, usize, usize) {
+        (self.pos.0, self.pos.1, self.size.0, self.size.1)
+    }
+
+    fn draw(&self, canvas: &mut Canvas, theme: &UiTheme, focused: bool) {
+        let lines = self.lines();
+        let end = lines.len().saturating_sub(self.scroll);
+        let start = end.saturating_sub(self.size.1);
+        for (row, (line, color)) in lines[start..end].iter().enumerate() {
+            let y = self.pos.1 + row as i32;
+            match color {
+                Color::Default => canvas.print_str(self.pos.0, y, line),
+                _ => canvas.print_colored(self.pos.0, y, line, *color, theme.bg)
+            }
+        }
+        if focused && self.scroll > 0 {
+            // Show there's more below
+            canvas.set(
+                self.pos.0 + self.size.0 as i32 - 1, self.pos.1 + self.size.1 as i32 - 1,
+                theme.focus_right
+            );
+        }
+    }
+
+    fn on_key(&mut self, code: VirtualKeyCode, _ctx: &mut UiContext) -> bool {
+        match code {
+            VirtualKeyCode::Up => self.scroll_by(1),
+            VirtualKeyCode::Down => self.scroll_by(-1),
+            VirtualKeyCode::PageUp => self.scroll_by(self.size.1 as i32),
+            VirtualKeyCode::PageDown => self.scroll_by(-(self.size.1 as i32)),
+            VirtualKeyCode::Home => self.scroll_by(i32::MAX / 2),
+            VirtualKeyCode::End => self.scroll = 0,
+            _ => return false
+        }
+        true
+    }
+
+    fn on_mouse(&mut self, event: MouseEvent, _ctx: &mut UiContext) {
+        if let MouseEvent::Scrolled(lines, _) = event {
+            self.scroll_by(lines);
+        }
+    }
+
+    fn on_message(&mut self, channel: &str, text: &str) {
+        if channel == self.channel {
+            self.push_colored(text, self.color);
+        }
+    }
+}
+
+/// A message log on its own, placed anywhere on screen and fed by messages on its channel.
+/// It scrolls with page up, page down, home, end and the mouse wheel, leaving the arrow keys
+/// to the game
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MessageWindow {
+    pos: (i32, i32),
+    size: (usize, usize),
+    title: Option<String>,
+    log: MessageLog,
+    theme: UiTheme,
+    layer: i32
+}
+
+impl MessageWindow {
+    /// An unframed log keeping up to history messages, drawn on layer 100
+    pub fn new(pos: (i32, i32), size: (usize, usize), history: usize) -> Self {
+        Self {
+            pos,
+            size,
+            title: None,
+            log: MessageLog::new(pos, size, history),
+            theme: UiTheme::default(),
+            layer: 100
+        }
+    }
+
+    /// Draw a filled frame around the log, with a title in the top edge.
+    /// The log shrinks to fit inside it
+    pub fn set_frame(&self, title: &str) -> Self {
+        let mut new = self.clone();
+        new.title = Some(title.to_string());
+        new.log.set_rect(
+            (self.pos.0 + 1, self.pos.1 + 1),
+            (self.size.0.saturating_sub(2), self.size.1.saturating_sub(2))
+        );
+        new
+    }
+
+    pub fn set_channel(&self, channel: &str) -> Self {
+        let mut new = self.clone();
+        new.log = new.log.set_channel(channel);
+        new
+    }
+
+    pub fn set_color(&self, color: Color) -> Self {
+        let mut new = self.clone();
+        new.log = new.log.set_color(color);
+        new
+    }
+
+    pub fn set_theme(&self, theme: UiTheme) -> Self {
+        let mut new = self.clone();
+        new.theme = theme;
+        new
+    }
+
+    pub fn set_layer(&self, layer: i32) -> Self {
+        let mut new = self.clone();
+        new.layer = layer;
+        new
+    }
+
+    pub fn log(&self) -> &MessageLog {
+        &self.log
+    }
+
+    pub fn log_mut(&mut self) -> &mut MessageLog {
+        &mut self.log
+    }
+}
+
+impl GameObject for MessageWindow {
+    fn obj_type(&self) -> String {
+        "MessageWindow".to_string()
+    }
+
+    fn persistent(&self) -> bool {
+        true
+    }
+
+    fn on_key_pressed(
+            &mut self, code: VirtualKeyCode,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            cur_room: &mut String) {
+        if matches!(
+            code,
+            VirtualKeyCode::PageUp | VirtualKeyCode::PageDown
+                | VirtualKeyCode::Home | VirtualKeyCode::End
+        ) {
+            self.log.on_key(code, &mut UiContext { cur_room, close: false });
+        }
+    }
+
+    fn on_key_released(
+            &mut self, _code: VirtualKeyCode,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            _cur_room: &mut String) {}
+
+    fn on_mouse(
+            &mut self, event: MouseEvent,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            cur_room: &mut String) {
+        if self.log.contains(event.pos()) {
+            self.log.on_mouse(event, &mut UiContext { cur_room, close: false });
+        }
+    }
+
+    fn on_message(
+            &mut self, channel: &str, text: &str,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            _cur_room: &mut String) {
+        self.log.on_message(channel, text);
+    }
+
+    fn update(
+            &mut self, _delta_time: f32,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            _cur_room: &mut String) {}
+
+    fn draw(&self, canvas: &mut Canvas) {
+        if let Some(title) = &self.title {
+            let (x, y) = self.pos;
+            canvas.fill_rect(x, y, self.size.0, self.size.1, self.theme.fill);
+            canvas.color_rect(x, y, self.size.0, self.size.1, self.theme.fg, self.theme.bg);
+            canvas.draw_frame(x, y, self.size.0, self.size.1, self.theme.frame, title);
+        }
+        self.log.draw(canvas, &self.theme, true);
+    }
+
+    fn layer(&self) -> i32 {
+        self.layer
+    }
+}
+
+/// A group of widgets drawn together, with Tab, Shift+Tab and the arrow keys moving focus
+/// between them. Panels can have a frame, open and close on a key, and be modal, taking all
+/// input while they're open, which makes a dialog box
+#[derive(Clone)]
+pub struct Panel {
+    widgets: Vec<Box<dyn Widget>>,
+    focus: Option<usize>,
+    theme: UiTheme,
+    frame: Option<((i32, i32, usize, usize), String)>,
+    layer: i32,
+    modal: bool,
+    open: bool,
+    toggle_key: Option<VirtualKeyCode>,
+    persistent: bool,
+    shift_held: bool
+}
+
+impl Default for Panel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Panel {
+    /// An open, unframed panel drawn on layer 100, over most things
+    pub fn new() -> Self {
+        Self {
+            widgets: Vec::new(),
+            focus: None,
+            theme: UiTheme::default(),
+            frame: None,
+            layer: 100,
+            modal: false,
+            open: true,
+            toggle_key: None,
+            persistent: true,
+            shift_held: false
+        }
+    }
+
+    /// A modal, framed panel that starts closed. Give it a key to open it,
+    /// and have a button set close on its context to shut it
+    pub fn dialog(rect: (i32, i32, usize, usize), title: &str) -> Self {
+        Self::new().set_frame(rect, title).set_modal(true).set_open(false)
+    }
+
+    /// Add a widget. The first one that can be focused starts with focus
+    pub fn add_widget(&self, widget: Box<dyn Widget>) -> Self {
+        let mut new = self.clone();
+        if new.focus.is_none() && widget.focusable() {
+            new.focus = Some(new.widgets.len());
+        }
+        new.widgets.push(widget);
+        new
+    }
+
+    pub fn set_theme(&self, theme: UiTheme) -> Self {
+        let mut new = self.clone();
+        new.theme = theme;
+        new
+    }
+
+    /// Draw a filled frame around the panel, with a title in the top edge
+    pub fn set_frame(&self, rect: (i32, i32, usize, usize), title: &str) -> Self {
+        let mut new = self.clone();
+        new.frame = Some((rect, title.to_string()));
+        new
+    }
+
+    pub fn set_layer(&self, layer: i32) -> Self {
+        let mut new = self.clone();
+        new.layer = layer;
+        new
+    }
+
+    pub fn set_modal(&self, modal: bool) -> Self {
+        let mut new = self.clone();
+        new.modal = modal;
+        new
+    }
+
+    pub fn set_open(&self, open: bool) -> Self {
+        let mut new = self.clone();
+        new.open = open;
+        new
+    }
+
+    /// A key that opens and closes the panel, like escape for a pause menu
+    pub fn set_toggle_key(&self, key: Option<VirtualKeyCode>) -> Self {
+        let mut new = self.clone();
+        new.toggle_key = key;
+        new
+    }
+
+    /// Whether the panel keeps its state when its room is left (it does by default)
+    pub fn set_persistent(&self, persistent: bool) -> Self {
+        let mut new = self.clone();
+        new.persistent = persistent;
+        new
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// The widget that has focus, if any
+    pub fn focus(&self) -> Option<usize> {
+        self.focus
+    }
+
+    /// Move focus forward or back to the next widget that can take it, wrapping around
+    fn move_focus(&mut self, forward: bool) {
+        let count = self.widgets.len();
+        let start = self.focus.unwrap_or(if forward { count - 1 } else { 0 });
+        for step in 1..=count {
+            let index = (if forward { start + step } else { start + count - step }) % count;
+            if self.widgets[index].focusable() {
+                self.focus = Some(index);
+                return;
+            }
+        }
+    }
+
+    /// Run some input on a widget, closing the panel if it asked to be
+    fn with_widget<F>(&mut self, index: usize, cur_room: &mut String, input: F)
+            where F: FnOnce(&mut Box<dyn Widget>, &mut UiContext) {
+        let mut ctx = UiContext {
+            cur_room,
+            close: false
+        };
+        input(&mut self.widgets[index], &mut ctx);
+        if ctx.close {
+            self.open = false;
+        }
+    }
+}
+
+impl GameObject for Panel {
+    fn obj_type(&self) -> String {
+        "Panel".to_string()
+    }
+
+    fn persistent(&self) -> bool {
+        self.persistent
+    }
+
+    fn on_key_pressed(
+            &mut self, code: VirtualKeyCode,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            cur_room: &mut String) {
+        if matches!(code, VirtualKeyCode::LShift | VirtualKeyCode::RShift) {
+            self.shift_held = true;
+        }
+        if Some(code) == self.toggle_key {
+            self.open = !self.open;
+            return;
+        }
+        if !self.open {
+            return;
+        }
+        let mut used = false;
+        if let Some(index) = self.focus {
+            self.with_widget(index, cur_room, |widget, ctx| used = widget.on_key(code, ctx));
+        }
+        if !used && !self.widgets.is_empty() {
+            match code {
+                VirtualKeyCode::Tab => self.move_focus(!self.shift_held),
+                VirtualKeyCode::Up | VirtualKeyCode::Left => self.move_focus(false),
+                VirtualKeyCode::Down | VirtualKeyCode::Right => self.move_focus(true),
+                _ => {}
+            }
+        }
+    }
+
+    fn on_key_released(
+            &mut self, code: VirtualKeyCode,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            _cur_room: &mut String) {
+        if matches!(code, VirtualKeyCode::LShift | VirtualKeyCode::RShift) {
+            self.shift_held = false;
+        }
+    }
+
+    fn on_char_typed(
+            &mut self, c: char,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            cur_room: &mut String) {
+        if let (true, Some(index)) = (self.open, self.focus) {
+            self.with_widget(index, cur_room, |widget, ctx| widget.on_char(c, ctx));
+        }
+    }
+
+    fn on_mouse(
+            &mut self, event: MouseEvent,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            cur_room: &mut String) {
+        if !self.open {
+            return;
+        }
+        let hovered = self.widgets.iter().rposition(|widget| widget.contains(event.pos()));
+        if let Some(index) = hovered {
+            if matches!(event, MouseEvent::Pressed(..)) && self.widgets[index].focusable() {
+                self.focus = Some(index);
+            }
+            self.with_widget(index, cur_room, |widget, ctx| widget.on_mouse(event, ctx));
+        }
+    }
+
+    fn on_message(
+            &mut self, channel: &str, text: &str,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            _cur_room: &mut String) {
+        for widget in self.widgets.iter_mut() {
+            widget.on_message(channel, text);
+        }
+    }
+
+    fn update(
+            &mut self, _delta_time: f32,
+            _global_objs: &Vec<Box<dyn GameObject>>,
+            _rooms: &HashMap<String, Vec<Box<dyn GameObject>>>,
+            _cur_room: &mut String) {}
+
+    fn draw(&self, canvas: &mut Canvas) {
+        if !self.open {
+            return;
+        }
+        if let Some(((x, y, width, height), title)) = &self.frame {
+            canvas.fill_rect(*x, *y, *width, *height, self.theme.fill);
+            canvas.color_rect(*x, *y, *width, *height, self.theme.fg, self.theme.bg);
+            canvas.draw_frame(*x, *y, *width, *height, self.theme.frame, title);
+        }
+        for (index, widget) in self.widgets.iter().enumerate() {
+            widget.draw(canvas, &self.theme, self.focus == Some(index));
+        }
+    }
+
+    fn layer(&self) -> i32 {
+        self.layer
+    }
+
+    fn modal(&self) -> bool {
+        self.modal && self.open
+    }
+}
@@ -0,0 +1,238 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Record rendered frames into an animated GIF or APNG
+
+use std::{
+    fs::{
+        self, File
+    }, io::{
+        BufReader, BufWriter, Read, Write
+    }, sync::mpsc::{
+        self, SyncSender
+    }, thread::{
+        self, JoinHandle
+    }, time::{
+        SystemTime, UNIX_EPOCH
+    }
+};
+use gif::{
+    Encoder, Frame, Repeat
+};
+
+/// Frames are sampled at this rate. GIF delays are in hundredths of a second, so keep it a divisor
+const VIDEO_FRAME_RATE: u16 = 20;
+/// Quantization speed for GIF palettes from 1 (best) to 30 (fastest)
+const GIF_SPEED: i32 = 10;
+/// Frames that can wait for the encoder before recording has to wait for it to catch up
+const QUEUED_FRAMES: usize = 30;
+
+/// A file path to save a recording to based on the current time
+pub fn default_path() -> String {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or(0);
+    format!("recording_{}.gif", millis)
+}
+
+/// Animation formats that can be recorded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoFormat {
+    Gif,
+    Apng
+}
+
+impl VideoFormat {
+    /// Pick a format from a file's extension: .gif for GIF, .png or .apng for APNG
+    pub fn from_path(path: &str) -> Option<Self> {
+        let ext = path.rsplit_once('.').map(|(_, ext)| ext.to_lowercase());
+        match ext.as_deref() {
+            Some("gif") => Some(Self::Gif),
+            Some("png") | Some("apng") => Some(Self::Apng),
+            _ => None
+        }
+    }
+}
+
+/// Where finished frames go. APNG needs the frame count up front, so those are spooled to a file
+/// next to the recording and encoded from there at the end, one at a time
+enum VideoOutput {
+    Gif(Encoder<BufWriter<File>>),
+    Apng {
+        file: File,
+        spool_path: String,
+        spool: BufWriter<File>,
+        frames: u32
+    }
+}
+
+impl VideoOutput {
+    /// Write a frame that lasts some number of samples
+    fn write_frame(
+            &mut self, width: u16, height: u16, mut pixels: Vec<u8>,
+            samples: u16) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            VideoOutput::Gif(encoder) => {
+                let mut frame = Frame::from_rgba_speed(width, height, &mut pixels, GIF_SPEED);
+                frame.delay = samples * (100 / VIDEO_FRAME_RATE);
+                encoder.write_frame(&frame)?;
+            }, VideoOutput::Apng { spool, frames, .. } => {
+                spool.write_all(&samples.to_le_bytes())?;
+                spool.write_all(&pixels)?;
+                *frames += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish the file once every frame's been written
+    fn finish(self, width: u16, height: u16) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            // The GIF trailer is written when the encoder drops
+            VideoOutput::Gif(_) => {},
+            VideoOutput::Apng { file, spool_path, spool, frames } => {
+                drop(spool);
+                let encoded = encode_apng(file, &spool_path, width, height, frames);
+                fs::remove_file(&spool_path)?;
+                encoded?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Encode the frames spooled to a file as an APNG
+fn encode_apng(
+        file: File, spool_path: &str, width: u16, height: u16,
+        frames: u32) -> Result<(), Box<dyn std::error::Error>> {
+    if frames == 0 {
+        return Ok(());
+    }
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames, 0)?;
+    let mut writer = encoder.write_header()?;
+    let mut spool = BufReader::new(File::open(spool_path)?);
+    let mut samples = [0; 2];
+    let mut pixels = vec![0; width as usize * height as usize * 4];
+    for _ in 0..frames {
+        spool.read_exact(&mut samples)?;
+        spool.read_exact(&mut pixels)?;
+        writer.set_frame_delay(u16::from_le_bytes(samples), VIDEO_FRAME_RATE)?;
+        writer.write_image_data(&pixels)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Samples RGBA frames at a fixed rate, merging frames that don't change into longer ones.
+/// Frames are encoded on their own thread, so recording doesn't hold up the game
+pub struct VideoRecorder {
+    frames: Option<SyncSender<(Vec<u8>, u16)>>,
+    encoder: Option<JoinHandle<Result<(), String>>>,
+
+    /// The frame waiting to find out how long it's shown for, and the sample it started on
+    pending: Option<(Vec<u8>, u64)>,
+    last_sample: Option<u64>
+}
+
+impl VideoRecorder {
+    /// Start a recording. The format is picked from the path's extension
+    pub fn create(
+            path: &str, width: u32,
+            height: u32) -> Result<Self, Box<dyn std::error::Error>> {
+        let format = VideoFormat::from_path(path)
+            .ok_or("Recordings must be saved as .gif, .png or .apng.")?;
+        let width = u16::try_from(width)?;
+        let height = u16::try_from(height)?;
+        let file = File::create(path)?;
+        let mut output = match format {
+            VideoFormat::Gif => {
+                let mut encoder = Encoder::new(BufWriter::new(file), width, height, &[])?;
+                encoder.set_repeat(Repeat::Infinite)?;
+                VideoOutput::Gif(encoder)
+            }, VideoFormat::Apng => {
+                let spool_path = format!("{}.frames", path);
+                VideoOutput::Apng {
+                    file,
+                    spool: BufWriter::new(File::create(&spool_path)?),
+                    spool_path,
+                    frames: 0
+                }
+            }
+        };
+
+        let (frames, queue) = mpsc::sync_channel::<(Vec<u8>, u16)>(QUEUED_FRAMES);
+        let encoder = thread::spawn(move || {
+            for (pixels, samples) in queue {
+                output.write_frame(width, height, pixels, samples)
+                    .map_err(|err| err.to_string())?;
+            }
+            output.finish(width, height).map_err(|err| err.to_string())
+        });
+        Ok(Self {
+            frames: Some(frames),
+            encoder: Some(encoder),
+            pending: None,
+            last_sample: None
+        })
+    }
+
+    /// Whether a frame at this time (seconds since recording began) would actually be used.
+    /// Reading frames back from the gpu is slow, so check this first
+    pub fn wants_frame(&self, time: f64) -> bool {
+        let sample = (time * VIDEO_FRAME_RATE as f64) as u64;
+        self.last_sample.map(|last| sample > last).unwrap_or(true)
+    }
+
+    /// Add a frame of RGBA pixels shown at a time in seconds since recording began
+    pub fn record(
+            &mut self, time: f64, pixels: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.wants_frame(time) {
+            return Ok(());
+        }
+        let sample = (time * VIDEO_FRAME_RATE as f64) as u64;
+        self.last_sample = Some(sample);
+        match self.pending.take() {
+            Some((last_pixels, start)) if last_pixels == pixels =>
+                self.pending = Some((last_pixels, start)),
+            Some((last_pixels, start)) => {
+                self.write_frame(last_pixels, sample - start)?;
+                self.pending = Some((pixels, sample));
+            }, None => self.pending = Some((pixels, sample))
+        }
+        Ok(())
+    }
+
+    /// Write out the last frame and wait for the file to be finished
+    pub fn finish(mut self, time: f64) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some((pixels, start)) = self.pending.take() {
+            let end = (time * VIDEO_FRAME_RATE as f64) as u64;
+            self.write_frame(pixels, end.saturating_sub(start).max(1))?;
+        }
+        self.frames = None;
+        self.wait_for_encoder()
+    }
+
+    /// Hand a frame that lasts some number of samples to the encoder
+    fn write_frame(
+            &mut self, pixels: Vec<u8>,
+            samples: u64) -> Result<(), Box<dyn std::error::Error>> {
+        let samples = samples.min(u16::MAX as u64 / 100) as u16;
+        let sent = self.frames.as_ref().map(|frames| frames.send((pixels, samples)).is_ok());
+        if sent != Some(true) {
+            // The encoder only hangs up when it fails
+            self.frames = None;
+            self.wait_for_encoder()?;
+            Err("Video encoder stopped.")?;
+        }
+        Ok(())
+    }
+
+    /// Wait for the encoder to stop, returning how it went
+    fn wait_for_encoder(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(encoder) = self.encoder.take() {
+            encoder.join().map_err(|_| "Video encoder panicked.")??;
+        }
+        Ok(())
+    }
+}
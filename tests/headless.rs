@@ -0,0 +1,29 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Running games without a window
+
+use pseudo_term::{
+    cmd::{
+        self, Command
+    }, env::EnvironmentBuilder,
+    obj::GameObject
+};
+
+#[test]
+fn cast_started_partway_through_begins_at_zero() {
+    let path = std::env::temp_dir().join(format!("pseudo_term_cast_{}.cast", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    let room: Vec<Box<dyn GameObject>> = Vec::new();
+    let builder = EnvironmentBuilder::new("room").add_room("room", &room);
+    let mut env = pollster::block_on(builder.build_headless()).unwrap();
+    env.run(30).unwrap();
+
+    cmd::send(Command::StartCast(path.clone()));
+    env.run(5).unwrap();
+    env.stop_cast().unwrap();
+
+    let cast = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let first_event = cast.lines().nth(1).unwrap();
+    let time: f64 = first_event[1..first_event.find(',').unwrap()].parse().unwrap();
+    assert!(time.abs() < 1e-6, "first event at {}", time);
+}
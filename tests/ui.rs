@@ -0,0 +1,63 @@
+// Author(s): Dylan Turner <dylan.turner@tutanota.com>
+//! Widgets and the message log
+
+use pseudo_term::{
+    canvas::Canvas,
+    color::Color,
+    obj::GameObject,
+    ui::{
+        MessageLog, MessageWindow, Slider, UiTheme, Widget
+    }
+};
+
+#[test]
+fn slider_with_reversed_range() {
+    let slider = Slider::new((0, 0), 10, (10.0, 0.0), 1.0, 20.0, |_, _| {});
+    assert_eq!(slider.value(), 10.0);
+    let slider = Slider::new((0, 0), 10, (10.0, 0.0), 1.0, -5.0, |_, _| {});
+    assert_eq!(slider.value(), 0.0);
+    let slider = Slider::new((0, 0), 10, (10.0, 0.0), 1.0, 4.0, |_, _| {});
+    assert_eq!(slider.value(), 4.0);
+}
+
+#[test]
+fn slider_with_nan_bound() {
+    let slider = Slider::new((0, 0), 10, (f32::NAN, 5.0), 1.0, 8.0, |_, _| {});
+    assert_eq!(slider.value(), 5.0);
+    let slider = Slider::new((0, 0), 10, (0.0, 1.0), 0.1, f32::NAN, |_, _| {});
+    assert_eq!(slider.value(), 0.0);
+}
+
+#[test]
+fn message_log_colors() {
+    let mut log = MessageLog::new((0, 0), (20, 5), 10);
+    log.push_colored("You hit the rat", Color::Red);
+    log.push_colored("You hit the rat", Color::Red);
+    log.push_colored("You hit the rat", Color::Green);
+    log.push("You hit the rat");
+    assert_eq!(
+        log.messages(),
+        vec![ "You hit the rat x2", "You hit the rat", "You hit the rat" ]
+    );
+
+    let mut canvas = Canvas::new();
+    log.draw(&mut canvas, &UiTheme::default(), false);
+    let colors = canvas.color_buffer();
+    assert_eq!(colors[0][0], (Color::Red, Color::Default));
+    assert_eq!(colors[1][0], (Color::Green, Color::Default));
+    assert_eq!(colors[2][0], (Color::Default, Color::Default));
+}
+
+#[test]
+fn message_window_colors_its_channel() {
+    let mut window = MessageWindow::new((0, 0), (20, 5), 10)
+        .set_channel("combat")
+        .set_color(Color::BrightRed);
+    window.log_mut().on_message("combat", "The rat bites");
+    window.log_mut().on_message("log", "Ignored");
+
+    let mut canvas = Canvas::new();
+    window.draw(&mut canvas);
+    assert_eq!(window.log().messages(), vec![ "The rat bites" ]);
+    assert_eq!(canvas.color_buffer()[0][0], (Color::BrightRed, Color::Default));
+}